@@ -0,0 +1,4 @@
+#!/bin/bash
+# Usage: ./scripts/decode.sh input.packed|input.png [output.csv] [--schema schema.json | --schema-name name] [--photo] [--palette palette.json]
+
+cargo run --bin decode -- "$@"
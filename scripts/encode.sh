@@ -1,5 +1,5 @@
 #!/bin/bash
-# Usage: ./scripts/encode.sh input.csv [output.png] [packed.packed] [--schema schema.json] [--palette palette.json]
+# Usage: ./scripts/encode.sh input.csv [output.png] [packed.packed] [--schema schema.json | --schema-name name] [--palette palette.json] [--module-size pixels] [--ecc none|low|medium|high] [--embed-schema]
 
 cargo run --bin encode -- "$@"
 
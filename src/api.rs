@@ -1,13 +1,120 @@
 use crate::common::color_palette::{load_color_palette, palette_to_bgr, usable_color_set};
 use crate::common::constants::DATA_COLOR_SEQUENCE;
-use crate::common::schema::load_schema;
-use crate::encoder::data_packer::{encode, read_csv};
+use crate::common::schema::{load_schema, load_schema_document};
+use crate::encoder::data_packer::{encode_with_options, read_csv, PackOptions};
+use crate::common::color_palette::BgrColor;
+use crate::common::data_regions::ImageLayout;
+use crate::common::fec::EccLevel;
+use crate::common::header_strip::MAX_LAYOUT_FIELD;
+use crate::decoder::data_unpacker::{decode, write_csv};
+use crate::decoder::image_reader::{read_encoded_image, read_encoded_photo, read_photo_header};
+use crate::decoder::sampling_grid::SamplingGrid;
+use crate::decoder::tag_detector::locate_corner_tags;
 use crate::encoder::image_generator::{calculate_minimum_image_size, create_encoded_image};
 use image::ImageEncoder;
 
+const PADDING: usize = 4;
+const TAG_DATA_GAP: usize = 1;
+const DATA_PADDING: usize = 4;
+const START_IMAGE_SIZE: usize = 20;
+
+/// Layout knobs for `encode_csv_to_image_with_options`. All of them are recorded in
+/// the image's header strip, so decoders never need to be told about them. The strip
+/// stores each size in 4 bits, so they are limited to `MAX_LAYOUT_FIELD` (15). The
+/// tags need a blank module on every side, so `padding` is at least 1 and the data
+/// regions start at least one module past the tags:
+/// `data_padding + tag_data_gap >= padding + 1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncodeOptions {
+    /// Blank modules between the image edge and the tags, at most 15.
+    pub padding: usize,
+    /// Blank modules between the tags and the data regions, at most 15.
+    pub tag_data_gap: usize,
+    /// Blank modules between the image edge and the data regions, at most 15.
+    pub data_padding: usize,
+    /// Pixels per module side in the output PNG, 1 to 15.
+    pub module_size: usize,
+    /// Reed-Solomon parity added after compression so misread cells can be corrected.
+    pub ecc_level: EccLevel,
+    /// Embed the resolved schema in the payload so it decodes without a schema file.
+    pub embed_schema: bool,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        EncodeOptions {
+            padding: PADDING,
+            tag_data_gap: TAG_DATA_GAP,
+            data_padding: DATA_PADDING,
+            module_size: 1,
+            ecc_level: EccLevel::None,
+            embed_schema: false,
+        }
+    }
+}
+
+impl EncodeOptions {
+    /// Checks that every layout option fits its header strip field and that the data
+    /// regions leave the tags and header strip a blank border.
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        if self.module_size == 0 {
+            return Err(anyhow::anyhow!("module_size must be at least 1"));
+        }
+        let fields = [
+            ("module_size", self.module_size),
+            ("padding", self.padding),
+            ("tag_data_gap", self.tag_data_gap),
+            ("data_padding", self.data_padding),
+        ];
+        for (name, value) in fields {
+            if value > MAX_LAYOUT_FIELD {
+                return Err(anyhow::anyhow!(
+                    "{} = {} exceeds {}, the most the image header can record",
+                    name,
+                    value,
+                    MAX_LAYOUT_FIELD
+                ));
+            }
+        }
+        if self.padding == 0 {
+            return Err(anyhow::anyhow!("padding must be at least 1 to leave a blank border around the tags"));
+        }
+        // Tags span padding..padding + tag size and data starts at
+        // data_padding + tag size + tag_data_gap, from each edge.
+        if self.data_padding + self.tag_data_gap < self.padding + 1 {
+            return Err(anyhow::anyhow!(
+                "data_padding {} + tag_data_gap {} must be at least padding {} + 1, or data is drawn over the tags",
+                self.data_padding,
+                self.tag_data_gap,
+                self.padding
+            ));
+        }
+        Ok(())
+    }
+}
+
 pub struct EncodeResult {
     pub image_bytes: Vec<u8>,
     pub packed_data: Vec<u8>,
+    /// Schema resolution warnings, then rows that failed a warning-severity rule.
+    pub warnings: Vec<String>,
+}
+
+pub struct DecodeResult {
+    pub csv_bytes: Vec<u8>,
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+fn resolve_palette_bgr(palette_bytes: Option<&[u8]>) -> Result<Vec<BgrColor>, anyhow::Error> {
+    if let Some(palette_bytes) = palette_bytes {
+        let palette_rgb = load_color_palette(palette_bytes)?;
+        let usable_palette_rgb = usable_color_set(&palette_rgb);
+        Ok(palette_to_bgr(&usable_palette_rgb))
+    } else {
+        let default_palette_rgb: Vec<_> = DATA_COLOR_SEQUENCE.to_vec();
+        Ok(palette_to_bgr(&default_palette_rgb))
+    }
 }
 
 pub fn encode_csv_to_image(
@@ -15,40 +122,53 @@ pub fn encode_csv_to_image(
     schema_bytes: Option<&[u8]>,
     palette_bytes: Option<&[u8]>,
 ) -> Result<EncodeResult, anyhow::Error> {
-    let schema = load_schema(schema_bytes)?;
+    encode_csv_to_image_with_options(csv_bytes, schema_bytes, palette_bytes, &EncodeOptions::default())
+}
+
+pub fn encode_csv_to_image_with_options(
+    csv_bytes: &[u8],
+    schema_bytes: Option<&[u8]>,
+    palette_bytes: Option<&[u8]>,
+    options: &EncodeOptions,
+) -> Result<EncodeResult, anyhow::Error> {
+    options.validate()?;
+
+    let (document, mut warnings) = load_schema_document(schema_bytes)?;
     
-    let palette_bgr = if let Some(palette_bytes) = palette_bytes {
-        let palette_rgb = load_color_palette(palette_bytes)?;
-        let usable_palette_rgb = usable_color_set(&palette_rgb);
-        palette_to_bgr(&usable_palette_rgb)
-    } else {
-        let default_palette_rgb: Vec<_> = DATA_COLOR_SEQUENCE.iter().copied().collect();
-        palette_to_bgr(&default_palette_rgb)
-    };
+    let palette_bgr = resolve_palette_bgr(palette_bytes)?;
     
     let (headers, rows) = read_csv(csv_bytes)?;
     
-    let packed_data = encode(&headers, &rows, Some(&schema))?;
-    
-    let padding = 4;
-    let tag_data_gap = 1;
-    let data_padding = 4;
+    let pack_options = PackOptions {
+        embed_schema: options.embed_schema,
+        rules: document.rules,
+    };
+    let packed = encode_with_options(&headers, &rows, Some(&document.columns), &pack_options)?;
+    let packed_data = packed.packed;
+    warnings.extend(packed.warnings);
     
     let image_size = calculate_minimum_image_size(
         &packed_data,
-        tag_data_gap,
-        data_padding,
-        20,
+        options.padding,
+        options.tag_data_gap,
+        options.data_padding,
+        START_IMAGE_SIZE,
+        options.ecc_level,
         &palette_bgr,
     )?;
     
+    let layout = ImageLayout {
+        image_width: image_size,
+        image_height: image_size,
+        padding: options.padding,
+        tag_data_gap: options.tag_data_gap,
+        data_padding: options.data_padding,
+    };
     let encoded_image = create_encoded_image(
         &packed_data,
-        image_size,
-        image_size,
-        padding,
-        tag_data_gap,
-        data_padding,
+        &layout,
+        options.module_size,
+        options.ecc_level,
         Some(&palette_bgr),
     )?;
     
@@ -66,6 +186,238 @@ pub fn encode_csv_to_image(
     Ok(EncodeResult {
         image_bytes: png_bytes,
         packed_data,
+        warnings,
+    })
+}
+
+/// Decodes a lossless PNG produced by `encode_csv_to_image` back to CSV bytes.
+/// `schema_bytes` must match the schema used to encode unless the payload embeds
+/// its own or was encoded with a built-in schema; the palette size and layout are
+/// read from the image's header strip.
+///
+/// `palette_bytes` is accepted for compatibility and ignored: the colours come from
+/// the calibration cells drawn in every image.
+pub fn decode_image_to_csv(
+    png_bytes: &[u8],
+    schema_bytes: Option<&[u8]>,
+    _palette_bytes: Option<&[u8]>,
+) -> Result<DecodeResult, anyhow::Error> {
+    let schema = schema_bytes.map(|bytes| load_schema(Some(bytes))).transpose()?;
+    
+    let image = image::load_from_memory(png_bytes)
+        .map_err(|e| anyhow::anyhow!("Invalid image: {}", e))?
+        .to_rgb8();
+    
+    let packed_data = read_encoded_image(&image)?;
+    
+    let (headers, rows) = decode(&packed_data, schema.as_deref())?;
+    let csv_bytes = write_csv(&headers, &rows)?;
+    
+    Ok(DecodeResult {
+        csv_bytes,
+        headers,
+        rows,
+    })
+}
+
+/// Decodes a camera photo of an image made by `encode_csv_to_image`. The three
+/// corner tags anchor a perspective-corrected sampling grid over the data regions,
+/// and the header strip next to tag 0 supplies the layout and palette size.
+/// `palette_bytes` is ignored, as in `decode_image_to_csv`.
+pub fn decode_photo_to_csv(
+    photo_bytes: &[u8],
+    schema_bytes: Option<&[u8]>,
+    _palette_bytes: Option<&[u8]>,
+) -> Result<DecodeResult, anyhow::Error> {
+    let schema = schema_bytes.map(|bytes| load_schema(Some(bytes))).transpose()?;
+    
+    let photo = image::load_from_memory(photo_bytes)
+        .map_err(|e| anyhow::anyhow!("Invalid image: {}", e))?
+        .to_rgb8();
+    
+    let tags = locate_corner_tags(&photo)?;
+    let header = read_photo_header(&photo, &tags[0])?;
+    
+    let grid = SamplingGrid::from_tags(&tags, &header.layout)?;
+    let packed_data = read_encoded_photo(&photo, &grid, &header)?;
+    
+    let (headers, rows) = decode(&packed_data, schema.as_deref())?;
+    let csv_bytes = write_csv(&headers, &rows)?;
+    
+    Ok(DecodeResult {
+        csv_bytes,
+        headers,
+        rows,
     })
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::homography::{Homography, Point};
+
+    #[test]
+    fn rule_warnings_are_returned_not_printed() {
+        let schema = br#"{
+            "columns": [{ "name": "a", "kind": "int", "int_max": 9 }],
+            "rules": [{ "kind": "compare", "left": "a", "op": ">", "right": 0, "severity": "warning" }]
+        }"#;
+        let result = encode_csv_to_image(b"a\n1\n0\n", Some(schema), None).unwrap();
+        assert_eq!(result.warnings, vec![r#"Row 2: rule 'a > 0' failed (a="0")"#.to_string()]);
+    }
+
+    const LAYOUT_CSV_ROWS: usize = 400;
+
+    fn layout_csv() -> Vec<u8> {
+        let mut csv = String::from("a,b\n");
+        for i in 0..LAYOUT_CSV_ROWS {
+            csv.push_str(&format!("{},{}\n", i % 1000, (i * 7919) % 1000));
+        }
+        csv.into_bytes()
+    }
+
+    const LAYOUT_SCHEMA: &[u8] = br#"[{ "name": "a", "kind": "int", "int_max": 999 },
+                                      { "name": "b", "kind": "int", "int_max": 999 }]"#;
+
+    fn encode_layout(options: EncodeOptions) -> Result<EncodeResult, anyhow::Error> {
+        encode_csv_to_image_with_options(&layout_csv(), Some(LAYOUT_SCHEMA), None, &options)
+    }
+
+    fn layout(padding: usize, tag_data_gap: usize, data_padding: usize) -> EncodeOptions {
+        EncodeOptions { padding, tag_data_gap, data_padding, ..EncodeOptions::default() }
+    }
+
+    #[test]
+    fn layout_options_must_fit_the_header_strip() {
+        for options in [
+            EncodeOptions { module_size: 0, ..EncodeOptions::default() },
+            EncodeOptions { module_size: 16, ..EncodeOptions::default() },
+            layout(16, 1, 15),
+            layout(4, 16, 4),
+            layout(4, 1, 16),
+        ] {
+            assert!(options.validate().is_err(), "{:?}", options);
+            assert!(encode_layout(options).is_err(), "{:?}", options);
+        }
+    }
+
+    #[test]
+    fn layouts_that_draw_over_the_tags_are_rejected() {
+        for options in [layout(4, 1, 0), layout(10, 1, 2), layout(0, 1, 0), layout(0, 1, 4), layout(4, 0, 4)] {
+            assert!(options.validate().is_err(), "{:?}", options);
+            assert!(encode_layout(options).is_err(), "{:?}", options);
+        }
+    }
+
+    #[test]
+    fn accepted_layouts_round_trip_through_both_decoders() {
+        let expected = decode_image_to_csv(&encode_layout(EncodeOptions::default()).unwrap().image_bytes, Some(LAYOUT_SCHEMA), None)
+            .unwrap()
+            .csv_bytes;
+        for padding in [1, 4, 15] {
+            for tag_data_gap in [0, 3] {
+                for data_padding in [padding + 1 - tag_data_gap.min(padding + 1), 15] {
+                    let options = layout(padding, tag_data_gap, data_padding);
+                    if options.validate().is_err() {
+                        continue;
+                    }
+                    let image = encode_layout(options).unwrap().image_bytes;
+                    let lossless = decode_image_to_csv(&image, Some(LAYOUT_SCHEMA), None);
+                    assert_eq!(lossless.unwrap().csv_bytes, expected, "{:?}", options);
+                    let photo = decode_photo_to_csv(&image, Some(LAYOUT_SCHEMA), None);
+                    assert_eq!(photo.unwrap().csv_bytes, expected, "{:?}", options);
+                }
+            }
+        }
+    }
+
+    const PHOTO_CSV: &[u8] = b"team,score\n254,87\n1678,112\n971,45\n118,0\n2056,130\n";
+    const PHOTO_SCHEMA: &[u8] = br#"[{ "name": "team", "kind": "int", "int_max": 9999 },
+                                     { "name": "score", "kind": "int", "int_max": 255 }]"#;
+
+    /// An encoded image at 4 pixels per module, as a camera would see it flat on.
+    fn photo_source() -> image::RgbImage {
+        let options = EncodeOptions { module_size: 4, ..EncodeOptions::default() };
+        let result = encode_csv_to_image_with_options(PHOTO_CSV, Some(PHOTO_SCHEMA), None, &options).unwrap();
+        image::load_from_memory(&result.image_bytes).unwrap().to_rgb8()
+    }
+
+    /// Renders a `width` x `height` photo whose pixel centers come from `source` in the
+    /// encoded image, sampled bilinearly, on a white background.
+    fn warp(image: &image::RgbImage, width: u32, height: u32, source: impl Fn(Point) -> Point) -> image::RgbImage {
+        let pixel = |x: i64, y: i64| {
+            if x < 0 || y < 0 || x >= image.width() as i64 || y >= image.height() as i64 {
+                [255.0; 3]
+            } else {
+                image.get_pixel(x as u32, y as u32).0.map(f64::from)
+            }
+        };
+        image::RgbImage::from_fn(width, height, |x, y| {
+            let (sx, sy) = source((x as f64 + 0.5, y as f64 + 0.5));
+            let (sx, sy) = (sx - 0.5, sy - 0.5);
+            let (x0, y0) = (sx.floor(), sy.floor());
+            let (fx, fy) = (sx - x0, sy - y0);
+            let (x0, y0) = (x0 as i64, y0 as i64);
+            let mut out = [0u8; 3];
+            for (c, value) in out.iter_mut().enumerate() {
+                let v = pixel(x0, y0)[c] * (1.0 - fx) * (1.0 - fy)
+                    + pixel(x0 + 1, y0)[c] * fx * (1.0 - fy)
+                    + pixel(x0, y0 + 1)[c] * (1.0 - fx) * fy
+                    + pixel(x0 + 1, y0 + 1)[c] * fx * fy;
+                *value = v.round() as u8;
+            }
+            image::Rgb(out)
+        })
+    }
+
+    fn decode_photo(photo: &image::RgbImage) -> Result<DecodeResult, anyhow::Error> {
+        let mut png = Vec::new();
+        photo.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)?;
+        decode_photo_to_csv(&png, Some(PHOTO_SCHEMA), None)
+    }
+
+    #[test]
+    fn scaled_photos_decode() {
+        let image = photo_source();
+        let (w, h) = (image.width() as f64 * 1.5, image.height() as f64 * 1.5);
+        let photo = warp(&image, w as u32 + 20, h as u32 + 20, |(x, y)| ((x - 10.0) / 1.5, (y - 10.0) / 1.5));
+        assert_eq!(decode_photo(&photo).unwrap().csv_bytes, PHOTO_CSV);
+    }
+
+    #[test]
+    fn rotated_photos_decode() {
+        let image = photo_source();
+        let (w, h) = (image.width() as f64, image.height() as f64);
+        let size = (w.hypot(h) + 20.0) as u32;
+        let (sin, cos) = 20f64.to_radians().sin_cos();
+        let center = size as f64 / 2.0;
+        let photo = warp(&image, size, size, |(x, y)| {
+            let (dx, dy) = (x - center, y - center);
+            (cos * dx + sin * dy + w / 2.0, -sin * dx + cos * dy + h / 2.0)
+        });
+        assert_eq!(decode_photo(&photo).unwrap().csv_bytes, PHOTO_CSV);
+    }
+
+    #[test]
+    fn perspective_photos_decode() {
+        let image = photo_source();
+        let (w, h) = (image.width() as f64, image.height() as f64);
+        // The top edge leans away from the camera: narrower and shorter than the bottom.
+        let photo_corners = [(40.0, 20.0), (w * 1.2 - 10.0, 35.0), (w * 1.3 + 20.0, h * 1.3), (10.0, h * 1.25)];
+        let image_corners = [(0.0, 0.0), (w, 0.0), (w, h), (0.0, h)];
+        let to_image = Homography::estimate(&photo_corners.into_iter().zip(image_corners).collect::<Vec<_>>()).unwrap();
+        let photo = warp(&image, (w * 1.3) as u32 + 40, (h * 1.3) as u32 + 20, |p| to_image.apply(p));
+        assert_eq!(decode_photo(&photo).unwrap().csv_bytes, PHOTO_CSV);
+    }
+
+    #[test]
+    fn tinted_photos_decode() {
+        let mut photo = photo_source();
+        // Warm, dim indoor light: every channel scaled down, blue the most.
+        for pixel in photo.pixels_mut() {
+            let [r, g, b] = pixel.0.map(f64::from);
+            pixel.0 = [(r * 0.85 + 10.0) as u8, (g * 0.7 + 5.0) as u8, (b * 0.5) as u8];
+        }
+        assert_eq!(decode_photo(&photo).unwrap().csv_bytes, PHOTO_CSV);
+    }
+}
@@ -0,0 +1,114 @@
+use std::path::PathBuf;
+use std::fs;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().collect();
+    
+    if args.len() < 2 {
+        eprintln!("Usage: {} <packed_or_png_path> [output_csv_path] [--schema <schema_path> | --schema-name <builtin_name>] [--photo] [--palette <palette_path>]", args[0]);
+        std::process::exit(1);
+    }
+    
+    let input_path = PathBuf::from(&args[1]);
+    let mut output_csv_path = input_path.with_extension("csv");
+    let mut schema_path = None;
+    let mut schema_name = None;
+    let mut is_photo = false;
+    
+    let mut i = 2;
+    if let Some(arg) = args.get(2) {
+        if !arg.starts_with("--") {
+            output_csv_path = PathBuf::from(arg);
+            i = 3;
+        }
+    }
+    
+    while i < args.len() {
+        match args[i].as_str() {
+            "--schema" => {
+                if i + 1 < args.len() {
+                    schema_path = Some(PathBuf::from(&args[i + 1]));
+                    i += 2;
+                } else {
+                    eprintln!("Error: --schema requires a path");
+                    std::process::exit(1);
+                }
+            }
+            "--schema-name" => {
+                if i + 1 < args.len() {
+                    schema_name = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    eprintln!(
+                        "Error: --schema-name requires one of: {}",
+                        scouting_data_compression::common::schema_registry::builtin_schema_names().join(", ")
+                    );
+                    std::process::exit(1);
+                }
+            }
+            "--photo" => {
+                is_photo = true;
+                i += 1;
+            }
+            "--palette" => {
+                // Still accepted so existing scripts keep working; the colours come
+                // from the image's calibration cells.
+                if i + 1 < args.len() {
+                    eprintln!("[warning] --palette is ignored: the palette is read from the image");
+                    i += 2;
+                } else {
+                    eprintln!("Error: --palette requires a path");
+                    std::process::exit(1);
+                }
+            }
+            _ => {
+                eprintln!("Unknown argument: {}", args[i]);
+                std::process::exit(1);
+            }
+        }
+    }
+    
+    if !input_path.exists() {
+        eprintln!("Error: input file not found: {}", input_path.display());
+        std::process::exit(1);
+    }
+    
+    let input_bytes = fs::read(&input_path)?;
+    let schema_bytes = match (&schema_path, schema_name) {
+        (Some(_), Some(_)) => {
+            eprintln!("Error: --schema and --schema-name cannot be combined");
+            std::process::exit(1);
+        }
+        (Some(path), None) => Some(fs::read(path)?),
+        (None, Some(name)) => Some(serde_json::to_vec(&name)?),
+        (None, None) => None,
+    };
+    
+    let is_png = input_path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+    let (csv_bytes, num_rows) = if is_photo {
+        let result = scouting_data_compression::api::decode_photo_to_csv(
+            &input_bytes,
+            schema_bytes.as_deref(),
+            None,
+        )?;
+        (result.csv_bytes, result.rows.len())
+    } else if is_png {
+        let result = scouting_data_compression::api::decode_image_to_csv(
+            &input_bytes,
+            schema_bytes.as_deref(),
+            None,
+        )?;
+        (result.csv_bytes, result.rows.len())
+    } else {
+        let schema = schema_bytes.as_deref()
+            .map(|bytes| scouting_data_compression::common::schema::load_schema(Some(bytes)))
+            .transpose()?;
+        let (headers, rows) = scouting_data_compression::decoder::data_unpacker::decode(&input_bytes, schema.as_deref())?;
+        (scouting_data_compression::decoder::data_unpacker::write_csv(&headers, &rows)?, rows.len())
+    };
+    
+    fs::write(&output_csv_path, &csv_bytes)?;
+    println!("Decoded {} rows to: {}", num_rows, output_csv_path.display());
+    
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use scouting_data_compression::common::header_strip::MAX_LAYOUT_FIELD;
 use std::path::PathBuf;
 use std::fs;
 
@@ -5,20 +6,22 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args: Vec<String> = std::env::args().collect();
     
     if args.len() < 2 {
-        eprintln!("Usage: {} <csv_path> [output_image_path] [packed_file_path] [--schema <schema_path>] [--palette <palette_path>]", args[0]);
+        eprintln!("Usage: {} <csv_path> [output_image_path] [packed_file_path] [--schema <schema_path> | --schema-name <builtin_name>] [--palette <palette_path>] [--module-size <pixels>] [--ecc <none|low|medium|high>] [--embed-schema]", args[0]);
         std::process::exit(1);
     }
     
     let csv_path = PathBuf::from(&args[1]);
     let output_image_path = args.get(2)
-        .map(|s| PathBuf::from(s))
+        .map(PathBuf::from)
         .unwrap_or_else(|| csv_path.with_extension("png"));
     let packed_file_path = args.get(3)
-        .map(|s| PathBuf::from(s))
+        .map(PathBuf::from)
         .unwrap_or_else(|| csv_path.with_extension("packed"));
     
     let mut schema_path = None;
+    let mut schema_name = None;
     let mut palette_path = None;
+    let mut options = scouting_data_compression::api::EncodeOptions::default();
     
     let mut i = 4;
     while i < args.len() {
@@ -32,6 +35,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     std::process::exit(1);
                 }
             }
+            "--schema-name" => {
+                if i + 1 < args.len() {
+                    schema_name = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    eprintln!(
+                        "Error: --schema-name requires one of: {}",
+                        scouting_data_compression::common::schema_registry::builtin_schema_names().join(", ")
+                    );
+                    std::process::exit(1);
+                }
+            }
             "--palette" => {
                 if i + 1 < args.len() {
                     palette_path = Some(PathBuf::from(&args[i + 1]));
@@ -41,6 +56,38 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     std::process::exit(1);
                 }
             }
+            "--module-size" => {
+                match args.get(i + 1).and_then(|v| v.parse::<usize>().ok()) {
+                    Some(module_size) if (1..=MAX_LAYOUT_FIELD).contains(&module_size) => {
+                        options.module_size = module_size;
+                        i += 2;
+                    }
+                    _ => {
+                        eprintln!("Error: --module-size requires an integer from 1 to {}", MAX_LAYOUT_FIELD);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--ecc" => {
+                match args.get(i + 1).map(|v| v.parse()) {
+                    Some(Ok(ecc_level)) => {
+                        options.ecc_level = ecc_level;
+                        i += 2;
+                    }
+                    Some(Err(e)) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                    None => {
+                        eprintln!("Error: --ecc requires a level");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--embed-schema" => {
+                options.embed_schema = true;
+                i += 1;
+            }
             _ => {
                 eprintln!("Unknown argument: {}", args[i]);
                 std::process::exit(1);
@@ -54,19 +101,30 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
     
     let csv_bytes = fs::read(&csv_path)?;
-    let schema_bytes = schema_path.as_ref()
-        .map(|p| fs::read(p))
-        .transpose()?;
+    let schema_bytes = match (&schema_path, schema_name) {
+        (Some(_), Some(_)) => {
+            eprintln!("Error: --schema and --schema-name cannot be combined");
+            std::process::exit(1);
+        }
+        (Some(path), None) => Some(fs::read(path)?),
+        (None, Some(name)) => Some(serde_json::to_vec(&name)?),
+        (None, None) => None,
+    };
     let palette_bytes = palette_path.as_ref()
-        .map(|p| fs::read(p))
+        .map(fs::read)
         .transpose()?;
     
-    let result = scouting_data_compression::api::encode_csv_to_image(
+    let result = scouting_data_compression::api::encode_csv_to_image_with_options(
         &csv_bytes,
         schema_bytes.as_deref(),
         palette_bytes.as_deref(),
+        &options,
     )?;
     
+    for warning in &result.warnings {
+        eprintln!("[warning] {}", warning);
+    }
+
     fs::write(&output_image_path, &result.image_bytes)?;
     println!("Encoded image saved to: {}", output_image_path.display());
     
@@ -35,6 +35,22 @@ pub fn load_april_tag(tag_id: u32) -> Result<GrayImage, anyhow::Error> {
     Ok(cropped)
 }
 
+/// Top-left `(row, col)` of a corner tag: 0 top-left, 1 top-right, 2 bottom-left.
+pub fn get_april_tag_origin(
+    tag_id: u32,
+    image_width: usize,
+    image_height: usize,
+    padding: usize,
+) -> Result<(usize, usize), anyhow::Error> {
+    let tag_size = get_april_tag_size();
+    match tag_id {
+        0 => Ok((padding, padding)),
+        1 => Ok((padding, image_width - padding - tag_size)),
+        2 => Ok((image_height - padding - tag_size, padding)),
+        _ => Err(anyhow::anyhow!("Unknown AprilTag id: {}", tag_id)),
+    }
+}
+
 pub fn generate_april_tags_image(
     image_width: usize,
     image_height: usize,
@@ -58,14 +74,10 @@ pub fn generate_april_tags_image(
         ));
     }
     
-    // Top-left: tag 0
-    place_tag(&mut image, &tag_0, padding, padding)?;
-    
-    // Top-right: tag 1
-    place_tag(&mut image, &tag_1, padding, image_width - padding - tag_size)?;
-    
-    // Bottom-left: tag 2
-    place_tag(&mut image, &tag_2, image_height - padding - tag_size, padding)?;
+    for (tag_id, tag) in [(0, &tag_0), (1, &tag_1), (2, &tag_2)] {
+        let (row, col) = get_april_tag_origin(tag_id, image_width, image_height, padding)?;
+        place_tag(&mut image, tag, row, col)?;
+    }
     
     Ok(image)
 }
@@ -1,4 +1,16 @@
 use crate::common::apriltag;
+use crate::common::header_strip::is_header_cell;
+
+/// Geometry of an encoded image, in modules (one module is `module_size` pixels square
+/// in the generated PNG).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageLayout {
+    pub image_width: usize,
+    pub image_height: usize,
+    pub padding: usize,
+    pub tag_data_gap: usize,
+    pub data_padding: usize,
+}
 
 pub struct DataRegion {
     pub row_start: usize,
@@ -15,47 +27,43 @@ pub fn get_data_regions(
 ) -> Vec<DataRegion> {
     let tag_size = apriltag::get_april_tag_size();
     
-    let mut regions = Vec::new();
-    
-    // Top region (between tag 0 and tag 1)
-    regions.push(DataRegion {
-        row_start: data_padding,
-        row_end: data_padding + tag_size + tag_data_gap,
-        col_start: data_padding + tag_size + tag_data_gap,
-        col_end: image_width - data_padding - tag_size - tag_data_gap,
-    });
-    
-    // Bottom region (extends to right edge)
-    regions.push(DataRegion {
-        row_start: image_height - data_padding - tag_size - tag_data_gap,
-        row_end: image_height - data_padding,
-        col_start: data_padding + tag_size + tag_data_gap,
-        col_end: image_width - data_padding,
-    });
-    
-    // Left region (between tag 0 and tag 2)
-    regions.push(DataRegion {
-        row_start: data_padding + tag_size + tag_data_gap,
-        row_end: image_height - data_padding - tag_size - tag_data_gap,
-        col_start: data_padding,
-        col_end: data_padding + tag_size + tag_data_gap,
-    });
-    
-    // Right region
-    regions.push(DataRegion {
-        row_start: data_padding + tag_size + tag_data_gap,
-        row_end: image_height - data_padding - tag_size - tag_data_gap,
-        col_start: image_width - data_padding - tag_size - tag_data_gap,
-        col_end: image_width - data_padding,
-    });
-    
-    // Center region
-    regions.push(DataRegion {
-        row_start: data_padding + tag_size + tag_data_gap,
-        row_end: image_height - data_padding - tag_size - tag_data_gap,
-        col_start: data_padding + tag_size + tag_data_gap,
-        col_end: image_width - data_padding - tag_size - tag_data_gap,
-    });
+    let mut regions = vec![
+        // Top region (between tag 0 and tag 1)
+        DataRegion {
+            row_start: data_padding,
+            row_end: data_padding + tag_size + tag_data_gap,
+            col_start: data_padding + tag_size + tag_data_gap,
+            col_end: image_width - data_padding - tag_size - tag_data_gap,
+        },
+        // Bottom region (extends to right edge)
+        DataRegion {
+            row_start: image_height - data_padding - tag_size - tag_data_gap,
+            row_end: image_height - data_padding,
+            col_start: data_padding + tag_size + tag_data_gap,
+            col_end: image_width - data_padding,
+        },
+        // Left region (between tag 0 and tag 2)
+        DataRegion {
+            row_start: data_padding + tag_size + tag_data_gap,
+            row_end: image_height - data_padding - tag_size - tag_data_gap,
+            col_start: data_padding,
+            col_end: data_padding + tag_size + tag_data_gap,
+        },
+        // Right region
+        DataRegion {
+            row_start: data_padding + tag_size + tag_data_gap,
+            row_end: image_height - data_padding - tag_size - tag_data_gap,
+            col_start: image_width - data_padding - tag_size - tag_data_gap,
+            col_end: image_width - data_padding,
+        },
+        // Center region
+        DataRegion {
+            row_start: data_padding + tag_size + tag_data_gap,
+            row_end: image_height - data_padding - tag_size - tag_data_gap,
+            col_start: data_padding + tag_size + tag_data_gap,
+            col_end: image_width - data_padding - tag_size - tag_data_gap,
+        },
+    ];
     
     regions.retain(|r| {
         let valid_row = r.row_start < r.row_end && r.row_start < image_height && r.row_end <= image_height;
@@ -66,3 +74,27 @@ pub fn get_data_regions(
     regions
 }
 
+
+/// Every data pixel as `(row, col)`, in the order the encoder fills them. Header strip
+/// cells are skipped.
+pub fn get_data_pixel_coords(layout: &ImageLayout) -> Vec<(usize, usize)> {
+    let data_regions = get_data_regions(
+        layout.image_width,
+        layout.image_height,
+        layout.tag_data_gap,
+        layout.data_padding,
+    );
+    
+    let mut pixel_coords = Vec::new();
+    for region in &data_regions {
+        for row in region.row_start..region.row_end {
+            for col in region.col_start..region.col_end {
+                if !is_header_cell(layout.padding, row, col) {
+                    pixel_coords.push((row, col));
+                }
+            }
+        }
+    }
+    
+    pixel_coords
+}
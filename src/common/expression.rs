@@ -0,0 +1,390 @@
+/// Arithmetic over other columns, used by computed columns. Grammar:
+///
+/// ```text
+/// expr    = term (("+" | "-") term)*
+/// term    = unary (("*" | "/") unary)*
+/// unary   = "-" unary | primary
+/// primary = number | column | function "(" expr ("," expr)* ")" | "(" expr ")"
+/// number  = digit+ ("." digit+)?
+/// ```
+///
+/// Columns are bare identifiers (letters, digits and `_`, not starting with a digit);
+/// the functions are `min`, `max` and `abs`. Expressions are at most `MAX_TOKENS`
+/// tokens long, and parentheses and calls nest at most `MAX_DEPTH` levels.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Number(f64),
+    Column(String),
+    Negate(Box<Expression>),
+    Binary(Box<Expression>, BinaryOp, Box<Expression>),
+    Call(Function, Vec<Expression>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Function {
+    Min,
+    Max,
+    Abs,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Symbol(char),
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, anyhow::Error> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() {
+            // Digits, optionally followed by '.' and more digits.
+            let mut end = skip_digits(start, &mut chars);
+            if let Some(&(_, '.')) = chars.peek() {
+                chars.next();
+                let fraction_end = skip_digits(end + 1, &mut chars);
+                if fraction_end == end + 1 {
+                    return Err(anyhow::anyhow!("expected digits after '{}'", &source[start..=end]));
+                }
+                end = fraction_end;
+            }
+            let number = source[start..end]
+                .parse()
+                .map_err(|_| anyhow::anyhow!("invalid number '{}'", &source[start..end]))?;
+            tokens.push(Token::Number(number));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some(&(i, d)) = chars.peek() {
+                if !(d.is_ascii_alphanumeric() || d == '_') {
+                    break;
+                }
+                end = i + d.len_utf8();
+                chars.next();
+            }
+            tokens.push(Token::Ident(source[start..end].to_string()));
+        } else if "+-*/(),".contains(c) {
+            tokens.push(Token::Symbol(c));
+            chars.next();
+        } else {
+            return Err(anyhow::anyhow!("unexpected character '{}'", c));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Consumes ASCII digits starting at byte `start` and returns the byte offset after them.
+fn skip_digits(start: usize, chars: &mut std::iter::Peekable<std::str::CharIndices>) -> usize {
+    let mut end = start;
+    while let Some(&(i, d)) = chars.peek() {
+        if !d.is_ascii_digit() {
+            break;
+        }
+        end = i + 1;
+        chars.next();
+    }
+    end
+}
+
+/// Deepest nesting of parentheses and function calls accepted, so a pathological
+/// schema can't overflow the stack while it loads.
+const MAX_DEPTH: usize = 32;
+
+/// Longest expression accepted, in tokens; this also bounds how deep a chain of
+/// `+` or `*` makes the tree that `evaluate` recurses over.
+const MAX_TOKENS: usize = 1024;
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek_symbol(&self) -> Option<char> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Symbol(c)) => Some(*c),
+            _ => None,
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: char) -> Result<(), anyhow::Error> {
+        if self.peek_symbol() != Some(symbol) {
+            return Err(anyhow::anyhow!("expected '{}'", symbol));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn expr(&mut self) -> Result<Expression, anyhow::Error> {
+        let mut left = self.term()?;
+        while let Some(op @ ('+' | '-')) = self.peek_symbol() {
+            self.pos += 1;
+            let op = if op == '+' { BinaryOp::Add } else { BinaryOp::Sub };
+            left = Expression::Binary(Box::new(left), op, Box::new(self.term()?));
+        }
+        Ok(left)
+    }
+
+    fn term(&mut self) -> Result<Expression, anyhow::Error> {
+        let mut left = self.unary()?;
+        while let Some(op @ ('*' | '/')) = self.peek_symbol() {
+            self.pos += 1;
+            let op = if op == '*' { BinaryOp::Mul } else { BinaryOp::Div };
+            left = Expression::Binary(Box::new(left), op, Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expression, anyhow::Error> {
+        let mut negations = 0;
+        while self.peek_symbol() == Some('-') {
+            self.pos += 1;
+            negations += 1;
+        }
+        let operand = self.primary()?;
+        // An even number of minus signs cancels out.
+        Ok(if negations % 2 == 1 {
+            Expression::Negate(Box::new(operand))
+        } else {
+            operand
+        })
+    }
+
+    fn primary(&mut self) -> Result<Expression, anyhow::Error> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(anyhow::anyhow!("expression nests more than {} levels deep", MAX_DEPTH));
+        }
+        let result = self.primary_inner();
+        self.depth -= 1;
+        result
+    }
+
+    fn primary_inner(&mut self) -> Result<Expression, anyhow::Error> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("unexpected end of expression"))?;
+        self.pos += 1;
+        match token {
+            Token::Number(n) => Ok(Expression::Number(n)),
+            Token::Symbol('(') => {
+                let inner = self.expr()?;
+                self.expect_symbol(')')?;
+                Ok(inner)
+            }
+            Token::Ident(name) if self.peek_symbol() == Some('(') => {
+                let function = match name.as_str() {
+                    "min" => Function::Min,
+                    "max" => Function::Max,
+                    "abs" => Function::Abs,
+                    _ => return Err(anyhow::anyhow!("unknown function '{}'", name)),
+                };
+                self.pos += 1;
+                let mut args = vec![self.expr()?];
+                while self.peek_symbol() == Some(',') {
+                    self.pos += 1;
+                    args.push(self.expr()?);
+                }
+                self.expect_symbol(')')?;
+                if function == Function::Abs && args.len() != 1 {
+                    return Err(anyhow::anyhow!("abs takes one argument, got {}", args.len()));
+                }
+                Ok(Expression::Call(function, args))
+            }
+            Token::Ident(name) => Ok(Expression::Column(name)),
+            Token::Symbol(c) => Err(anyhow::anyhow!("unexpected '{}'", c)),
+        }
+    }
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Expression, anyhow::Error> {
+        let tokens = tokenize(source)?;
+        if tokens.len() > MAX_TOKENS {
+            return Err(anyhow::anyhow!("expression is longer than {} tokens", MAX_TOKENS));
+        }
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            depth: 0,
+        };
+        let expression = parser.expr()?;
+        if parser.pos != parser.tokens.len() {
+            return Err(anyhow::anyhow!("unexpected trailing input"));
+        }
+        Ok(expression)
+    }
+
+    /// Every column the expression reads, in order of appearance.
+    pub fn columns(&self) -> Vec<&str> {
+        match self {
+            Expression::Number(_) => Vec::new(),
+            Expression::Column(name) => vec![name],
+            Expression::Negate(inner) => inner.columns(),
+            Expression::Binary(left, _, right) => {
+                let mut columns = left.columns();
+                columns.extend(right.columns());
+                columns
+            }
+            Expression::Call(_, args) => args.iter().flat_map(Expression::columns).collect(),
+        }
+    }
+
+    /// The expression's value given each column's number. `None` when a column has
+    /// no value or the result is not finite (e.g. division by zero).
+    pub fn evaluate(&self, column: &impl Fn(&str) -> Option<f64>) -> Option<f64> {
+        let value = match self {
+            Expression::Number(n) => *n,
+            Expression::Column(name) => column(name)?,
+            Expression::Negate(inner) => -inner.evaluate(column)?,
+            Expression::Binary(left, op, right) => {
+                let (a, b) = (left.evaluate(column)?, right.evaluate(column)?);
+                match op {
+                    BinaryOp::Add => a + b,
+                    BinaryOp::Sub => a - b,
+                    BinaryOp::Mul => a * b,
+                    BinaryOp::Div => a / b,
+                }
+            }
+            Expression::Call(function, args) => {
+                let values = args
+                    .iter()
+                    .map(|arg| arg.evaluate(column))
+                    .collect::<Option<Vec<_>>>()?;
+                match function {
+                    Function::Min => values.into_iter().fold(f64::INFINITY, f64::min),
+                    Function::Max => values.into_iter().fold(f64::NEG_INFINITY, f64::max),
+                    Function::Abs => values[0].abs(),
+                }
+            }
+        };
+        Some(value).filter(|v| v.is_finite())
+    }
+}
+
+/// Prints a computed value with `precision` decimal places, or when there is none as
+/// an integer if it is whole and otherwise with up to 6 decimal places.
+pub fn format_computed(value: f64, precision: Option<u32>) -> String {
+    let text = match precision {
+        Some(places) => format!("{:.*}", places as usize, value),
+        None if value.fract() == 0.0 && value.abs() < 1e15 => format!("{}", value as i64),
+        None => {
+            let text = format!("{:.6}", value);
+            text.trim_end_matches('0').trim_end_matches('.').to_string()
+        }
+    };
+    // Rounding can leave "-0" or "-0.00" behind.
+    match text.strip_prefix('-') {
+        Some(rest) if rest.chars().all(|c| c == '0' || c == '.') => rest.to_string(),
+        _ => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(source: &str) -> Option<f64> {
+        let columns = |name: &str| match name {
+            "a" => Some(6.0),
+            "b" => Some(4.0),
+            "zero" => Some(0.0),
+            _ => None,
+        };
+        Expression::parse(source).unwrap().evaluate(&columns)
+    }
+
+    #[test]
+    fn multiplication_binds_tighter_than_addition() {
+        assert_eq!(eval("2 + 3 * 4"), Some(14.0));
+        assert_eq!(eval("(2 + 3) * 4"), Some(20.0));
+        assert_eq!(eval("a - b - 1"), Some(1.0));
+        assert_eq!(eval("a / b / 2"), Some(0.75));
+    }
+
+    #[test]
+    fn unary_minus_applies_before_binary_operators() {
+        assert_eq!(eval("-a + b"), Some(-2.0));
+        assert_eq!(eval("-a * -b"), Some(24.0));
+        assert_eq!(eval("a - -b"), Some(10.0));
+        assert_eq!(eval("--a"), Some(6.0));
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(eval("min(a, b, 5)"), Some(4.0));
+        assert_eq!(eval("max(a, b * 2)"), Some(8.0));
+        assert_eq!(eval("abs(b - a)"), Some(2.0));
+        assert!(Expression::parse("abs(a, b)").is_err());
+        assert!(Expression::parse("sqrt(a)").is_err());
+    }
+
+    #[test]
+    fn missing_columns_and_division_by_zero_have_no_value() {
+        assert_eq!(eval("a / zero"), None);
+        assert_eq!(eval("zero / zero"), None);
+        assert_eq!(eval("a + missing"), None);
+    }
+
+    #[test]
+    fn columns_in_order_of_appearance() {
+        let expression = Expression::parse("max(b, a) - b * c").unwrap();
+        assert_eq!(expression.columns(), vec!["b", "a", "b", "c"]);
+    }
+
+    #[test]
+    fn malformed_expressions_are_rejected() {
+        for source in ["", "a +", "(a", "a b", "1.2.3", "a % b", "min()"] {
+            assert!(Expression::parse(source).is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn numbers_are_digits_with_an_optional_fraction() {
+        assert_eq!(eval("1.25 * 4"), Some(5.0));
+        let err = |source: &str| Expression::parse(source).unwrap_err().to_string();
+        assert_eq!(err("1.2.3"), "unexpected character '.'");
+        assert_eq!(err("."), "unexpected character '.'");
+        assert_eq!(err(".5"), "unexpected character '.'");
+        assert_eq!(err("1. + 2"), "expected digits after '1.'");
+    }
+
+    #[test]
+    fn long_minus_runs_and_deep_nesting_do_not_recurse_unbounded() {
+        let minuses = "-".repeat(MAX_TOKENS - 2);
+        assert_eq!(eval(&format!("{}a", minuses)), Some(6.0));
+        assert_eq!(eval(&format!("-{}a", minuses)), Some(-6.0));
+
+        let nested = |depth: usize| format!("{}a{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(eval(&nested(MAX_DEPTH - 1)), Some(6.0));
+        let err = Expression::parse(&nested(MAX_DEPTH + 1)).unwrap_err();
+        assert!(err.to_string().contains("nests more than"), "{}", err);
+
+        let chain = vec!["a"; MAX_TOKENS / 2].join("+");
+        assert_eq!(eval(&chain), Some(6.0 * (MAX_TOKENS / 2) as f64));
+        let err = Expression::parse(&vec!["a"; 100_000].join("+")).unwrap_err();
+        assert!(err.to_string().contains("longer than"), "{}", err);
+    }
+
+    #[test]
+    fn formatting() {
+        assert_eq!(format_computed(2.5, Some(2)), "2.50");
+        assert_eq!(format_computed(-0.001, Some(2)), "0.00");
+        assert_eq!(format_computed(7.0, None), "7");
+        assert_eq!(format_computed(1.0 / 3.0, None), "0.333333");
+        assert_eq!(format_computed(0.5, None), "0.5");
+    }
+}
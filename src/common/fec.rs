@@ -0,0 +1,471 @@
+use std::str::FromStr;
+
+/// How much Reed-Solomon parity is added to the packed payload before it is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EccLevel {
+    #[default]
+    None,
+    /// One parity byte per 8 data bytes: corrects about 6% bad bytes.
+    Low,
+    /// One parity byte per 4 data bytes: corrects about 10% bad bytes.
+    Medium,
+    /// One parity byte per 2 data bytes: corrects about 17% bad bytes.
+    High,
+}
+
+impl EccLevel {
+    pub fn code(self) -> u8 {
+        match self {
+            EccLevel::None => 0,
+            EccLevel::Low => 1,
+            EccLevel::Medium => 2,
+            EccLevel::High => 3,
+        }
+    }
+
+    pub fn from_code(code: u8) -> Result<Self, anyhow::Error> {
+        match code {
+            0 => Ok(EccLevel::None),
+            1 => Ok(EccLevel::Low),
+            2 => Ok(EccLevel::Medium),
+            3 => Ok(EccLevel::High),
+            _ => Err(anyhow::anyhow!("Unknown error correction level code {}", code)),
+        }
+    }
+
+    /// Parity bytes per data byte, as `(numerator, denominator)`.
+    fn parity_ratio(self) -> (usize, usize) {
+        match self {
+            EccLevel::None => (0, 1),
+            EccLevel::Low => (1, 8),
+            EccLevel::Medium => (1, 4),
+            EccLevel::High => (1, 2),
+        }
+    }
+}
+
+impl FromStr for EccLevel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(EccLevel::None),
+            "low" => Ok(EccLevel::Low),
+            "medium" => Ok(EccLevel::Medium),
+            "high" => Ok(EccLevel::High),
+            _ => Err(anyhow::anyhow!(
+                "Unknown error correction level '{}' (expected none, low, medium or high)",
+                s
+            )),
+        }
+    }
+}
+
+/// Codewords are at most 255 bytes, the GF(256) limit.
+const MAX_CODEWORD: usize = 255;
+
+/// Splits `data_len` bytes into Reed-Solomon blocks: returns each block's data
+/// length (the first blocks take one extra byte when it doesn't divide evenly)
+/// and the parity bytes shared by every block.
+fn block_plan(data_len: usize, level: EccLevel) -> (Vec<usize>, usize) {
+    let (num, den) = level.parity_ratio();
+    if num == 0 || data_len == 0 {
+        return (vec![data_len], 0);
+    }
+
+    let parity_for = |k: usize| {
+        let nsym = (k * num).div_ceil(den).max(2);
+        nsym + nsym % 2
+    };
+    let max_data = (1..MAX_CODEWORD)
+        .rev()
+        .find(|&k| k + parity_for(k) <= MAX_CODEWORD)
+        .unwrap_or(1);
+
+    let num_blocks = data_len.div_ceil(max_data);
+    let base = data_len / num_blocks;
+    let extra = data_len % num_blocks;
+    let lengths: Vec<usize> = (0..num_blocks).map(|i| base + usize::from(i < extra)).collect();
+    (lengths, parity_for(base + usize::from(extra > 0)))
+}
+
+/// Bytes drawn into the image for `data_len` payload bytes at `level`.
+pub fn protected_length(data_len: usize, level: EccLevel) -> usize {
+    let (lengths, nsym) = block_plan(data_len, level);
+    data_len + lengths.len() * nsym
+}
+
+/// Appends Reed-Solomon parity to each block and interleaves the codewords byte by
+/// byte, so a run of damaged cells is spread across many blocks.
+pub fn add_parity(data: &[u8], level: EccLevel) -> Vec<u8> {
+    let (lengths, nsym) = block_plan(data.len(), level);
+    if nsym == 0 {
+        return data.to_vec();
+    }
+
+    let generator = generator_poly(nsym);
+    let mut codewords = Vec::with_capacity(lengths.len());
+    let mut offset = 0;
+    for len in lengths {
+        let block = &data[offset..offset + len];
+        offset += len;
+        let mut codeword = block.to_vec();
+        codeword.extend(rs_parity(block, &generator));
+        codewords.push(codeword);
+    }
+
+    interleave(&codewords)
+}
+
+/// Inverse of `add_parity`: de-interleaves, corrects each block and returns the
+/// `data_len` payload bytes. `suspect` flags bytes read with low confidence; they
+/// are tried as erasures first, which doubles how many of them can be repaired.
+pub fn correct_errors(
+    protected: &[u8],
+    data_len: usize,
+    level: EccLevel,
+    suspect: &[bool],
+) -> Result<Vec<u8>, anyhow::Error> {
+    let expected = protected_length(data_len, level);
+    if protected.len() < expected {
+        return Err(anyhow::anyhow!(
+            "Protected payload has {} bytes, expected {}",
+            protected.len(),
+            expected
+        ));
+    }
+
+    let (lengths, nsym) = block_plan(data_len, level);
+    if nsym == 0 {
+        return Ok(protected[..data_len].to_vec());
+    }
+
+    let codeword_lengths: Vec<usize> = lengths.iter().map(|len| len + nsym).collect();
+    let positions = interleaved_positions(&codeword_lengths);
+
+    let mut data = Vec::with_capacity(data_len);
+    for (block, (len, block_positions)) in lengths.iter().zip(&positions).enumerate() {
+        let mut codeword: Vec<u8> = block_positions.iter().map(|&p| protected[p]).collect();
+        let erasures: Vec<usize> = block_positions
+            .iter()
+            .enumerate()
+            .filter(|&(_, &p)| suspect.get(p).copied().unwrap_or(false))
+            .map(|(i, _)| i)
+            .collect();
+
+        // A failed attempt can leave partial corrections behind, so the erasure
+        // attempt works on a copy and the fallback starts from the bytes as read.
+        let mut with_erasures = codeword.clone();
+        let corrected = if erasures.len() <= nsym
+            && rs_correct(&mut with_erasures, nsym, &erasures).is_some()
+        {
+            codeword = with_erasures;
+            true
+        } else {
+            rs_correct(&mut codeword, nsym, &[]).is_some()
+        };
+        if !corrected {
+            return Err(anyhow::anyhow!(
+                "Reed-Solomon block {} of {} has too many errors to correct",
+                block + 1,
+                lengths.len()
+            ));
+        }
+        data.extend_from_slice(&codeword[..*len]);
+    }
+    Ok(data)
+}
+
+fn interleave(codewords: &[Vec<u8>]) -> Vec<u8> {
+    let lengths: Vec<usize> = codewords.iter().map(Vec::len).collect();
+    let positions = interleaved_positions(&lengths);
+    let mut out = vec![0u8; lengths.iter().sum()];
+    for (codeword, block_positions) in codewords.iter().zip(&positions) {
+        for (&byte, &p) in codeword.iter().zip(block_positions) {
+            out[p] = byte;
+        }
+    }
+    out
+}
+
+/// For each codeword, the stream position of each of its bytes when codewords are
+/// interleaved round-robin (shorter codewords drop out once exhausted).
+fn interleaved_positions(lengths: &[usize]) -> Vec<Vec<usize>> {
+    let mut positions: Vec<Vec<usize>> = lengths.iter().map(|&len| Vec::with_capacity(len)).collect();
+    let longest = lengths.iter().copied().max().unwrap_or(0);
+    let mut next = 0;
+    for i in 0..longest {
+        for (block, &len) in lengths.iter().enumerate() {
+            if i < len {
+                positions[block].push(next);
+                next += 1;
+            }
+        }
+    }
+    positions
+}
+
+// GF(256) arithmetic with the 0x11d primitive polynomial and generator 2.
+
+const fn build_tables() -> ([u8; 512], [u8; 256]) {
+    let mut exp = [0u8; 512];
+    let mut log = [0u8; 256];
+    let mut x: u16 = 1;
+    let mut i = 0;
+    while i < 255 {
+        exp[i] = x as u8;
+        log[x as usize] = i as u8;
+        x <<= 1;
+        if x & 0x100 != 0 {
+            x ^= 0x11d;
+        }
+        i += 1;
+    }
+    while i < 512 {
+        exp[i] = exp[i - 255];
+        i += 1;
+    }
+    (exp, log)
+}
+
+const TABLES: ([u8; 512], [u8; 256]) = build_tables();
+const EXP: [u8; 512] = TABLES.0;
+const LOG: [u8; 256] = TABLES.1;
+
+fn gf_mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        0
+    } else {
+        EXP[LOG[a as usize] as usize + LOG[b as usize] as usize]
+    }
+}
+
+fn gf_inv(a: u8) -> u8 {
+    EXP[255 - LOG[a as usize] as usize]
+}
+
+fn gf_pow_alpha(power: usize) -> u8 {
+    EXP[power % 255]
+}
+
+/// `prod (x - alpha^i)` for `i in 0..nsym`, highest degree first.
+fn generator_poly(nsym: usize) -> Vec<u8> {
+    let mut g = vec![1u8];
+    for i in 0..nsym {
+        let root = gf_pow_alpha(i);
+        let mut next = vec![0u8; g.len() + 1];
+        for (j, &coef) in g.iter().enumerate() {
+            next[j] ^= coef;
+            next[j + 1] ^= gf_mul(coef, root);
+        }
+        g = next;
+    }
+    g
+}
+
+/// Remainder of `data * x^nsym` divided by the generator.
+fn rs_parity(data: &[u8], generator: &[u8]) -> Vec<u8> {
+    let nsym = generator.len() - 1;
+    let mut remainder = vec![0u8; nsym];
+    for &byte in data {
+        let factor = byte ^ remainder[0];
+        remainder.rotate_left(1);
+        remainder[nsym - 1] = 0;
+        for (r, &g) in remainder.iter_mut().zip(&generator[1..]) {
+            *r ^= gf_mul(g, factor);
+        }
+    }
+    remainder
+}
+
+/// Evaluates a polynomial stored highest degree first.
+fn eval_desc(poly: &[u8], x: u8) -> u8 {
+    poly.iter().fold(0u8, |acc, &c| gf_mul(acc, x) ^ c)
+}
+
+/// Evaluates a polynomial stored lowest degree first.
+fn eval_asc(poly: &[u8], x: u8) -> u8 {
+    poly.iter().rev().fold(0u8, |acc, &c| gf_mul(acc, x) ^ c)
+}
+
+/// Corrects `codeword` in place (byte 0 is the highest-degree coefficient) given the
+/// indices of known-bad bytes. Returns the number of bytes changed, or `None` when
+/// the damage is beyond what `nsym` parity bytes can repair; `codeword` is then left
+/// as it was.
+fn rs_correct(codeword: &mut [u8], nsym: usize, erasures: &[usize]) -> Option<usize> {
+    let n = codeword.len();
+    let syndromes: Vec<u8> = (0..nsym).map(|i| eval_desc(codeword, gf_pow_alpha(i))).collect();
+    if syndromes.iter().all(|&s| s == 0) {
+        return Some(0);
+    }
+
+    let locator_of = |index: usize| gf_pow_alpha(n - 1 - index);
+
+    // Berlekamp-Massey seeded with the erasure locator (all polynomials lowest degree first).
+    let mut lambda = vec![1u8];
+    for &e in erasures {
+        let x = locator_of(e);
+        let mut next = vec![0u8; lambda.len() + 1];
+        for (j, &coef) in lambda.iter().enumerate() {
+            next[j] ^= coef;
+            next[j + 1] ^= gf_mul(coef, x);
+        }
+        lambda = next;
+    }
+    let num_erasures = erasures.len();
+    let mut prev = lambda.clone();
+    let mut len = num_erasures;
+
+    for r in num_erasures..nsym {
+        let delta = (0..lambda.len().min(r + 1)).fold(0u8, |acc, j| acc ^ gf_mul(lambda[j], syndromes[r - j]));
+        prev.insert(0, 0);
+        if delta == 0 {
+            continue;
+        }
+
+        let mut next = lambda.clone();
+        next.resize(next.len().max(prev.len()), 0);
+        for (v, &p) in next.iter_mut().zip(&prev) {
+            *v ^= gf_mul(delta, p);
+        }
+        if 2 * len <= r + num_erasures {
+            let inv = gf_inv(delta);
+            prev = lambda.iter().map(|&c| gf_mul(c, inv)).collect();
+            len = r + 1 + num_erasures - len;
+        }
+        lambda = next;
+    }
+
+    while lambda.len() > 1 && lambda[lambda.len() - 1] == 0 {
+        lambda.pop();
+    }
+    let degree = lambda.len() - 1;
+    if degree != len || 2 * (len - num_erasures) + num_erasures > nsym {
+        return None;
+    }
+
+    // Chien search over the codeword positions.
+    let positions: Vec<usize> = (0..n)
+        .filter(|&i| eval_asc(&lambda, gf_inv(locator_of(i))) == 0)
+        .collect();
+    if positions.len() != degree {
+        return None;
+    }
+
+    // Forney: error value = X * omega(X^-1) / lambda'(X^-1).
+    let mut omega = vec![0u8; nsym];
+    for (i, &s) in syndromes.iter().enumerate() {
+        for (j, &l) in lambda.iter().enumerate() {
+            if i + j < nsym {
+                omega[i + j] ^= gf_mul(s, l);
+            }
+        }
+    }
+    let derivative: Vec<u8> = lambda
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, &c)| if i % 2 == 1 { c } else { 0 })
+        .collect();
+
+    let mut corrected = codeword.to_vec();
+    for &i in &positions {
+        let x = locator_of(i);
+        let x_inv = gf_inv(x);
+        let denominator = eval_asc(&derivative, x_inv);
+        if denominator == 0 {
+            return None;
+        }
+        let magnitude = gf_mul(x, gf_mul(eval_asc(&omega, x_inv), gf_inv(denominator)));
+        corrected[i] ^= magnitude;
+    }
+
+    if (0..nsym).any(|i| eval_desc(&corrected, gf_pow_alpha(i)) != 0) {
+        return None;
+    }
+    codeword.copy_from_slice(&corrected);
+    Some(positions.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 40 bytes at Medium is a single block with 10 parity bytes: up to 5 errors or
+    // 10 erasures can be repaired.
+    const DATA_LEN: usize = 40;
+    const NSYM: usize = 10;
+
+    fn data() -> Vec<u8> {
+        (0..DATA_LEN).map(|i| (i * 37 + 11) as u8).collect()
+    }
+
+    fn damaged(positions: &[usize]) -> Vec<u8> {
+        let mut protected = add_parity(&data(), EccLevel::Medium);
+        for &p in positions {
+            protected[p] ^= 0x5a;
+        }
+        protected
+    }
+
+    fn suspect(positions: &[usize], len: usize) -> Vec<bool> {
+        (0..len).map(|i| positions.contains(&i)).collect()
+    }
+
+    #[test]
+    fn plan_for_test_data_is_one_block() {
+        assert_eq!(block_plan(DATA_LEN, EccLevel::Medium), (vec![DATA_LEN], NSYM));
+    }
+
+    #[test]
+    fn corrects_up_to_half_the_parity_in_errors() {
+        let positions = [0, 7, 19, 33, 45];
+        let protected = damaged(&positions);
+        assert_eq!(correct_errors(&protected, DATA_LEN, EccLevel::Medium, &[]).unwrap(), data());
+    }
+
+    #[test]
+    fn fails_past_half_the_parity_in_errors() {
+        let positions = [0, 7, 19, 33, 45, 48];
+        let protected = damaged(&positions);
+        assert!(correct_errors(&protected, DATA_LEN, EccLevel::Medium, &[]).is_err());
+    }
+
+    #[test]
+    fn corrects_up_to_the_parity_in_erasures() {
+        let positions = [1, 4, 9, 16, 22, 25, 30, 38, 41, 49];
+        let protected = damaged(&positions);
+        let suspect = suspect(&positions, protected.len());
+        assert_eq!(correct_errors(&protected, DATA_LEN, EccLevel::Medium, &suspect).unwrap(), data());
+    }
+
+    #[test]
+    fn fails_past_the_parity_in_erasures() {
+        let positions = [1, 4, 9, 16, 22, 25, 30, 38, 41, 47, 49];
+        let protected = damaged(&positions);
+        let suspect = suspect(&positions, protected.len());
+        assert!(correct_errors(&protected, DATA_LEN, EccLevel::Medium, &suspect).is_err());
+    }
+
+    #[test]
+    fn wrong_erasure_hints_fall_back_to_the_bytes_as_read() {
+        // The suspect cells were read correctly; the real errors are elsewhere, too
+        // many to correct alongside four erasures but few enough on their own.
+        let errors = [3, 12, 27, 44];
+        let protected = damaged(&errors);
+        let suspect = suspect(&[0, 8, 20, 35], protected.len());
+        assert_eq!(correct_errors(&protected, DATA_LEN, EccLevel::Medium, &suspect).unwrap(), data());
+    }
+
+    #[test]
+    fn interleaving_spreads_a_burst_across_blocks() {
+        let data: Vec<u8> = (0..1000).map(|i| (i * 7) as u8).collect();
+        let mut protected = add_parity(&data, EccLevel::Low);
+        let (lengths, nsym) = block_plan(data.len(), EccLevel::Low);
+        let burst = lengths.len() * nsym / 2;
+        for byte in &mut protected[100..100 + burst] {
+            *byte = !*byte;
+        }
+        assert_eq!(correct_errors(&protected, data.len(), EccLevel::Low, &[]).unwrap(), data);
+    }
+}
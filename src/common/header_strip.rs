@@ -0,0 +1,220 @@
+use crate::common::apriltag::get_april_tag_size;
+use crate::common::data_regions::ImageLayout;
+use crate::common::fec::EccLevel;
+
+/// Layout version written into every header strip.
+pub const HEADER_VERSION: u8 = 1;
+
+/// The header strip sits level with tag 0, starting `HEADER_TAG_GAP` modules to
+/// its right, and holds one bit per module: black for 1, white for 0. Its position
+/// only depends on tag 0, so a decoder can read it before knowing anything else.
+pub const HEADER_ROWS: usize = 8;
+pub const HEADER_COLS: usize = 10;
+pub const HEADER_TAG_GAP: usize = 1;
+
+const HEADER_BITS: usize = HEADER_ROWS * HEADER_COLS;
+
+/// Everything a decoder needs to configure itself from the image alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageHeader {
+    pub version: u8,
+    /// Palette size is `1 << bits_per_pixel`.
+    pub bits_per_pixel: u32,
+    /// Rendered pixels per module along each side.
+    pub module_size: usize,
+    pub layout: ImageLayout,
+    /// Reed-Solomon parity added to the payload before it was drawn.
+    pub ecc_level: EccLevel,
+    /// Bytes of packed payload, before error correction parity is added.
+    pub payload_length: usize,
+}
+
+/// Largest module size, padding or gap the header's 4-bit layout fields can record.
+pub const MAX_LAYOUT_FIELD: usize = (1 << 4) - 1;
+
+/// Largest image side, in modules, the header's 10-bit size fields can record.
+pub const MAX_IMAGE_MODULES: usize = (1 << 10) - 1;
+
+/// Field widths in bits, in the order they are written.
+const FIELD_BITS: [u32; 10] = [4, 4, 4, 4, 4, 4, 4, 10, 10, 24];
+
+impl ImageHeader {
+    pub fn num_colors(&self) -> usize {
+        1 << self.bits_per_pixel
+    }
+
+    fn fields(&self) -> [u64; 10] {
+        [
+            self.version as u64,
+            self.bits_per_pixel as u64,
+            self.module_size as u64,
+            self.layout.padding as u64,
+            self.layout.tag_data_gap as u64,
+            self.layout.data_padding as u64,
+            self.ecc_level.code() as u64,
+            self.layout.image_width as u64,
+            self.layout.image_height as u64,
+            self.payload_length as u64,
+        ]
+    }
+
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        let names = [
+            "version",
+            "bits_per_pixel",
+            "module_size",
+            "padding",
+            "tag_data_gap",
+            "data_padding",
+            "ecc_level",
+            "image_width",
+            "image_height",
+            "payload_length",
+        ];
+        for ((value, bits), name) in self.fields().into_iter().zip(FIELD_BITS).zip(names) {
+            if value >= 1u64 << bits {
+                return Err(anyhow::anyhow!(
+                    "Header field {} = {} does not fit in {} bits",
+                    name,
+                    value,
+                    bits
+                ));
+            }
+        }
+        if self.module_size == 0 {
+            return Err(anyhow::anyhow!("Header module_size must be at least 1"));
+        }
+        if self.bits_per_pixel == 0 {
+            return Err(anyhow::anyhow!("Header bits_per_pixel must be at least 1"));
+        }
+        Ok(())
+    }
+
+    /// Header bits in cell order (see `header_cells`), fields MSB first, then a CRC-8.
+    pub fn to_bits(&self) -> Result<Vec<bool>, anyhow::Error> {
+        self.validate()?;
+
+        let mut bits = Vec::with_capacity(HEADER_BITS);
+        for (value, width) in self.fields().into_iter().zip(FIELD_BITS) {
+            for b in (0..width).rev() {
+                bits.push((value >> b) & 1 == 1);
+            }
+        }
+
+        let crc = crc8(&bits_to_bytes(&bits));
+        for b in (0..8).rev() {
+            bits.push((crc >> b) & 1 == 1);
+        }
+        Ok(bits)
+    }
+
+    pub fn from_bits(bits: &[bool]) -> Result<Self, anyhow::Error> {
+        if bits.len() != HEADER_BITS {
+            return Err(anyhow::anyhow!(
+                "Header strip has {} bits, expected {}",
+                bits.len(),
+                HEADER_BITS
+            ));
+        }
+
+        let (body, crc_bits) = bits.split_at(HEADER_BITS - 8);
+        let stored_crc = crc_bits.iter().fold(0u8, |acc, &b| (acc << 1) | b as u8);
+        if crc8(&bits_to_bytes(body)) != stored_crc {
+            return Err(anyhow::anyhow!("Header strip checksum mismatch"));
+        }
+
+        let mut fields = [0u64; 10];
+        let mut pos = 0;
+        for (field, width) in fields.iter_mut().zip(FIELD_BITS) {
+            for _ in 0..width {
+                *field = (*field << 1) | body[pos] as u64;
+                pos += 1;
+            }
+        }
+
+        let version = fields[0] as u8;
+        if version != HEADER_VERSION {
+            return Err(anyhow::anyhow!(
+                "Unsupported header layout version {} (expected {})",
+                version,
+                HEADER_VERSION
+            ));
+        }
+
+        let header = ImageHeader {
+            version,
+            bits_per_pixel: fields[1] as u32,
+            module_size: fields[2] as usize,
+            layout: ImageLayout {
+                padding: fields[3] as usize,
+                tag_data_gap: fields[4] as usize,
+                data_padding: fields[5] as usize,
+                image_width: fields[7] as usize,
+                image_height: fields[8] as usize,
+            },
+            ecc_level: EccLevel::from_code(fields[6] as u8)?,
+            payload_length: fields[9] as usize,
+        };
+        header.validate()?;
+        Ok(header)
+    }
+}
+
+/// `(row, col)` of every header cell relative to tag 0's top-left corner, in bit order.
+pub fn header_cells_from_tag() -> Vec<(usize, usize)> {
+    let first_col = get_april_tag_size() + HEADER_TAG_GAP;
+    (0..HEADER_ROWS)
+        .flat_map(|row| (0..HEADER_COLS).map(move |col| (row, first_col + col)))
+        .collect()
+}
+
+/// `(row, col)` of every header cell in image module coordinates, in bit order.
+pub fn header_cells(padding: usize) -> Vec<(usize, usize)> {
+    header_cells_from_tag()
+        .into_iter()
+        .map(|(row, col)| (padding + row, padding + col))
+        .collect()
+}
+
+/// Whether module `(row, col)` is reserved for the header strip, including the
+/// blank gap between it and tag 0.
+pub fn is_header_cell(padding: usize, row: usize, col: usize) -> bool {
+    let first_col = padding + get_april_tag_size();
+    (padding..padding + HEADER_ROWS).contains(&row)
+        && (first_col..first_col + HEADER_TAG_GAP + HEADER_COLS).contains(&col)
+}
+
+/// Whether the header strip fits between tag 0 and tag 1 with a one-module gap.
+pub fn header_fits(layout: &ImageLayout) -> bool {
+    let tag_size = get_april_tag_size();
+    let header_end = layout.padding + tag_size + HEADER_TAG_GAP + HEADER_COLS;
+    header_end + HEADER_TAG_GAP + tag_size + layout.padding <= layout.image_width
+        && layout.padding + HEADER_ROWS <= layout.image_height
+}
+
+fn bits_to_bytes(bits: &[bool]) -> Vec<u8> {
+    bits.chunks(8)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0u8, |acc, (i, &b)| acc | ((b as u8) << (7 - i)))
+        })
+        .collect()
+}
+
+/// CRC-8 with polynomial 0x07.
+fn crc8(bytes: &[u8]) -> u8 {
+    let mut crc = 0u8;
+    for &byte in bytes {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
@@ -2,4 +2,13 @@ pub mod apriltag;
 pub mod color_palette;
 pub mod constants;
 pub mod data_regions;
+pub mod expression;
+pub mod fec;
+pub mod header_strip;
+pub mod packed_header;
+pub mod rules;
 pub mod schema;
+pub mod schema_codec;
+pub mod schema_registry;
+pub mod string_table;
+pub mod values;
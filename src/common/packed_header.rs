@@ -0,0 +1,280 @@
+/// Packed payloads start with `SCOUTPK` followed by a format version byte.
+pub const PACKED_MAGIC: &[u8; 7] = b"SCOUTPK";
+pub const PACKED_VERSION: u8 = b'6';
+/// `SCOUTPK5`: magic and row count only, still accepted by the decoder.
+const LEGACY_VERSION: u8 = b'5';
+
+/// The compressed data starts with the resolved schema (see `schema_codec`).
+pub const FLAG_EMBEDDED_SCHEMA: u16 = 1 << 0;
+
+/// The header is followed by a length byte and the name of the built-in schema the
+/// payload was encoded with (see `schema_registry`).
+pub const FLAG_SCHEMA_NAME: u16 = 1 << 1;
+
+/// Feature flag bits this build understands; payloads with unknown bits set are
+/// rejected rather than misread.
+pub const KNOWN_FLAGS: u16 = FLAG_EMBEDDED_SCHEMA | FLAG_SCHEMA_NAME;
+
+const LEGACY_HEADER_LEN: usize = 12;
+const HEADER_LEN: usize = 22;
+/// Offset of the CRC32 field, which covers every other byte of the payload.
+const CRC_OFFSET: usize = 18;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackedHeader {
+    pub version: u8,
+    pub flags: u16,
+    pub num_rows: usize,
+    /// `schema_fingerprint` of the schema used to encode; absent in legacy payloads.
+    pub schema_fingerprint: Option<u32>,
+    /// Built-in schema the payload was encoded with, if it was one.
+    pub schema_name: Option<String>,
+}
+
+/// Prepends a current-version header to the compressed `body`. A `schema_name`
+/// sets `FLAG_SCHEMA_NAME` and is written right after the fixed header fields.
+pub fn write_packed(
+    flags: u16,
+    num_rows: usize,
+    schema_fingerprint: u32,
+    schema_name: Option<&str>,
+    body: &[u8],
+) -> Result<Vec<u8>, anyhow::Error> {
+    let num_rows = u32::try_from(num_rows)
+        .map_err(|_| anyhow::anyhow!("Too many rows to pack: {}", num_rows))?;
+    let flags = match schema_name {
+        Some(_) => flags | FLAG_SCHEMA_NAME,
+        None => flags & !FLAG_SCHEMA_NAME,
+    };
+
+    let mut packed = Vec::with_capacity(HEADER_LEN + body.len());
+    packed.extend_from_slice(PACKED_MAGIC);
+    packed.push(PACKED_VERSION);
+    packed.extend_from_slice(&flags.to_be_bytes());
+    packed.extend_from_slice(&num_rows.to_be_bytes());
+    packed.extend_from_slice(&schema_fingerprint.to_be_bytes());
+    packed.extend_from_slice(&[0; 4]);
+    if let Some(name) = schema_name {
+        let len = u8::try_from(name.len())
+            .map_err(|_| anyhow::anyhow!("Schema name is too long to record: {}", name))?;
+        packed.push(len);
+        packed.extend_from_slice(name.as_bytes());
+    }
+    packed.extend_from_slice(body);
+
+    let crc = payload_crc(&packed);
+    packed[CRC_OFFSET..HEADER_LEN].copy_from_slice(&crc.to_be_bytes());
+    Ok(packed)
+}
+
+fn payload_crc(packed: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(&packed[..CRC_OFFSET]);
+    hasher.update(&packed[HEADER_LEN..]);
+    hasher.finalize()
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+/// Parses and checks the header, returning it with the compressed body that follows.
+pub fn read_packed(packed: &[u8]) -> Result<(PackedHeader, &[u8]), anyhow::Error> {
+    if packed.len() < LEGACY_HEADER_LEN || &packed[..PACKED_MAGIC.len()] != PACKED_MAGIC {
+        return Err(anyhow::anyhow!("Not a SCOUTPK packed payload"));
+    }
+
+    match packed[PACKED_MAGIC.len()] {
+        LEGACY_VERSION => {
+            let header = PackedHeader {
+                version: LEGACY_VERSION,
+                flags: 0,
+                num_rows: read_u32(packed, 8) as usize,
+                schema_fingerprint: None,
+                schema_name: None,
+            };
+            Ok((header, &packed[LEGACY_HEADER_LEN..]))
+        }
+        PACKED_VERSION => {
+            if packed.len() < HEADER_LEN {
+                return Err(anyhow::anyhow!("Packed payload header is truncated"));
+            }
+            let stored_crc = read_u32(packed, CRC_OFFSET);
+            let actual_crc = payload_crc(packed);
+            if stored_crc != actual_crc {
+                return Err(anyhow::anyhow!(
+                    "Packed payload is corrupted (CRC32 {:08x}, expected {:08x})",
+                    actual_crc,
+                    stored_crc
+                ));
+            }
+
+            let flags = u16::from_be_bytes([packed[8], packed[9]]);
+            if flags & !KNOWN_FLAGS != 0 {
+                return Err(anyhow::anyhow!(
+                    "Packed payload uses unsupported feature flags {:#06x}",
+                    flags & !KNOWN_FLAGS
+                ));
+            }
+
+            let mut body = &packed[HEADER_LEN..];
+            let mut schema_name = None;
+            if flags & FLAG_SCHEMA_NAME != 0 {
+                let (&len, rest) = body
+                    .split_first()
+                    .ok_or_else(|| anyhow::anyhow!("Packed payload schema name is truncated"))?;
+                if rest.len() < len as usize {
+                    return Err(anyhow::anyhow!("Packed payload schema name is truncated"));
+                }
+                let (name, rest) = rest.split_at(len as usize);
+                let name = std::str::from_utf8(name)
+                    .map_err(|e| anyhow::anyhow!("Packed payload schema name is not UTF-8: {}", e))?;
+                schema_name = Some(name.to_string());
+                body = rest;
+            }
+
+            let header = PackedHeader {
+                version: PACKED_VERSION,
+                flags,
+                num_rows: read_u32(packed, 10) as usize,
+                schema_fingerprint: Some(read_u32(packed, 14)),
+                schema_name,
+            };
+            Ok((header, body))
+        }
+        version => Err(anyhow::anyhow!(
+            "Unsupported packed format version '{}'",
+            version as char
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::schema::load_schema;
+    use crate::decoder::data_unpacker::decode;
+    use crate::encoder::data_packer::{encode, read_csv};
+
+    const SCHEMA: &[u8] = br#"[{ "name": "team", "kind": "int", "int_max": 9999 },
+                               { "name": "match", "kind": "enum", "values": ["qm", "sf", "f"] }]"#;
+    const CSV: &[u8] = b"team,match\n254,qm\n1678,sf\n971,f\n";
+
+    /// `CSV` packed with `SCHEMA` by an encoder that predates the versioned header.
+    const LEGACY_PAYLOAD: [u8; 37] = [
+        0x53, 0x43, 0x4f, 0x55, 0x54, 0x50, 0x4b, 0x35, 0x00, 0x00, 0x00, 0x03,
+        0x28, 0xb5, 0x2f, 0xfd, 0x00, 0x88, 0x81, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x40, 0x60, 0x20, 0xe0, 0xa0, 0x80, 0x80, 0xe0, 0xc0, 0xe0, 0x20, 0x20,
+        0x40,
+    ];
+
+    fn packed(flags: u16) -> Vec<u8> {
+        write_packed(flags, 3, 0x1234_5678, None, b"compressed body").unwrap()
+    }
+
+    fn error(packed: &[u8]) -> String {
+        read_packed(packed).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn header_fields_round_trip() {
+        let packed = packed(FLAG_EMBEDDED_SCHEMA);
+        let (header, body) = read_packed(&packed).unwrap();
+        assert_eq!(
+            header,
+            PackedHeader {
+                version: PACKED_VERSION,
+                flags: FLAG_EMBEDDED_SCHEMA,
+                num_rows: 3,
+                schema_fingerprint: Some(0x1234_5678),
+                schema_name: None,
+            }
+        );
+        assert_eq!(body, b"compressed body");
+    }
+
+    #[test]
+    fn flipped_bytes_fail_the_crc() {
+        let packed = packed(0);
+        // Flags, row count, fingerprint, CRC and body are all covered.
+        for i in PACKED_MAGIC.len() + 1..packed.len() {
+            let mut corrupted = packed.clone();
+            corrupted[i] ^= 0x10;
+            assert!(error(&corrupted).contains("CRC32"), "byte {}", i);
+        }
+    }
+
+    #[test]
+    fn unknown_flag_bits_are_rejected() {
+        let err = error(&packed(1 << 9));
+        assert!(err.contains("unsupported feature flags 0x0200"), "{}", err);
+    }
+
+    #[test]
+    fn truncated_headers_are_errors() {
+        let packed = packed(0);
+        for len in 0..HEADER_LEN {
+            assert!(read_packed(&packed[..len]).is_err(), "{} bytes", len);
+        }
+        assert!(error(&packed[..HEADER_LEN - 1]).contains("truncated"));
+        assert!(error(b"SCOUTPK9 and the rest").contains("Unsupported packed format version '9'"));
+    }
+
+    #[test]
+    fn fingerprint_mismatch_names_both_fingerprints() {
+        let schema = load_schema(Some(SCHEMA)).unwrap();
+        let (headers, rows) = read_csv(CSV).unwrap();
+        let packed = encode(&headers, &rows, Some(&schema)).unwrap();
+
+        let mut other = schema.clone();
+        other.truncate(1);
+        let err = decode(&packed, Some(&other)).unwrap_err().to_string();
+        assert!(err.contains("encoded with a different schema (fingerprint"), "{}", err);
+    }
+
+    #[test]
+    fn legacy_payloads_still_decode() {
+        let (header, _) = read_packed(&LEGACY_PAYLOAD).unwrap();
+        assert_eq!((header.version, header.flags, header.num_rows), (LEGACY_VERSION, 0, 3));
+        assert_eq!(header.schema_fingerprint, None);
+
+        let schema = load_schema(Some(SCHEMA)).unwrap();
+        assert_eq!(decode(&LEGACY_PAYLOAD, Some(&schema)).unwrap(), read_csv(CSV).unwrap());
+    }
+
+    /// `packed` with its CRC recomputed, so a test can reach the checks after it.
+    fn recrc(mut packed: Vec<u8>) -> Vec<u8> {
+        let crc = payload_crc(&packed);
+        packed[CRC_OFFSET..HEADER_LEN].copy_from_slice(&crc.to_be_bytes());
+        packed
+    }
+
+    #[test]
+    fn schema_names_round_trip_and_bad_ones_are_errors() {
+        let named = write_packed(0, 3, 0, Some("frc-2025-reefscape"), b"body").unwrap();
+        let (header, body) = read_packed(&named).unwrap();
+        assert_eq!(header.flags, FLAG_SCHEMA_NAME);
+        assert_eq!(header.schema_name.as_deref(), Some("frc-2025-reefscape"));
+        assert_eq!(body, b"body");
+        // The flag follows the name, whatever the caller passed.
+        let unnamed = write_packed(FLAG_SCHEMA_NAME, 3, 0, None, b"body").unwrap();
+        assert_eq!(read_packed(&unnamed).unwrap().0.flags, 0);
+
+        let err = write_packed(0, 3, 0, Some(&"x".repeat(256)), b"").unwrap_err();
+        assert!(err.to_string().contains("too long"), "{}", err);
+
+        // Flag set, but the name is missing or shorter than its length byte.
+        let mut missing = packed(0);
+        missing.truncate(HEADER_LEN);
+        missing[9] |= FLAG_SCHEMA_NAME as u8;
+        let mut short = write_packed(0, 3, 0, Some("frc"), b"").unwrap();
+        short[HEADER_LEN] = 10;
+        for bad in [missing, short] {
+            assert!(error(&recrc(bad)).contains("schema name is truncated"));
+        }
+
+        let mut not_utf8 = write_packed(0, 3, 0, Some("frc"), b"").unwrap();
+        not_utf8[HEADER_LEN + 1] = 0xff;
+        assert!(error(&recrc(not_utf8)).contains("not UTF-8"));
+    }
+}
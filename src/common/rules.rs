@@ -0,0 +1,502 @@
+use crate::common::schema::{ColumnKind, ColumnSchema};
+use crate::common::values::{enum_lookup, normalize_cell, scalar_value, zigzag_decode};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Most failing rows listed in one error before the rest are summarized.
+const MAX_REPORTED_VIOLATIONS: usize = 20;
+
+/// A cross-column check from a schema's `rules` section, run on every row before
+/// packing. Rules are only checked at encode time; they are not stored in payloads.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind")]
+pub enum Rule {
+    /// `left op right` must hold, e.g. `AutonL1Scored <= AutonL1Attempted`.
+    #[serde(rename = "compare")]
+    Compare {
+        #[serde(flatten)]
+        check: Comparison,
+        #[serde(default)]
+        severity: Severity,
+        message: Option<String>,
+    },
+    /// Whenever `if` holds, `then` must hold too.
+    #[serde(rename = "implies")]
+    Implies {
+        #[serde(rename = "if")]
+        condition: Comparison,
+        then: Comparison,
+        #[serde(default)]
+        severity: Severity,
+        message: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The encode fails.
+    #[default]
+    Error,
+    /// The row is reported but still packed.
+    Warning,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Comparison {
+    pub left: Operand,
+    pub op: CompareOp,
+    pub right: Operand,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum CompareOp {
+    #[serde(rename = "==")]
+    Eq,
+    #[serde(rename = "!=")]
+    Ne,
+    #[serde(rename = "<")]
+    Lt,
+    #[serde(rename = "<=")]
+    Le,
+    #[serde(rename = ">")]
+    Gt,
+    #[serde(rename = ">=")]
+    Ge,
+}
+
+/// A bare string names a column and a bare number is a constant; `{"value": ...}`
+/// is a literal (e.g. an enum value) and `{"sum": [...]}` adds up numeric columns.
+/// Columns are read as the encoder stores them: enums as their canonical value,
+/// bools as 1 or 0 and decimals rounded to their step.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Operand {
+    Number(f64),
+    Column(String),
+    Literal { value: serde_json::Value },
+    Sum { sum: Vec<String> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(f64),
+    Text(String),
+}
+
+impl std::fmt::Display for CompareOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self {
+            CompareOp::Eq => "==",
+            CompareOp::Ne => "!=",
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+        };
+        f.write_str(symbol)
+    }
+}
+
+impl std::fmt::Display for Operand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operand::Number(n) => write!(f, "{}", n),
+            Operand::Column(name) => f.write_str(name),
+            Operand::Literal { value } => write!(f, "{}", value),
+            Operand::Sum { sum } => write!(f, "{}", sum.join(" + ")),
+        }
+    }
+}
+
+impl std::fmt::Display for Comparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.left, self.op, self.right)
+    }
+}
+
+impl Operand {
+    fn columns(&self) -> Vec<&str> {
+        match self {
+            Operand::Column(name) => vec![name],
+            Operand::Sum { sum } => sum.iter().map(String::as_str).collect(),
+            Operand::Number(_) | Operand::Literal { .. } => Vec::new(),
+        }
+    }
+
+    /// The operand's value in one row, or `None` when a column it reads is empty.
+    fn evaluate(&self, row: &RowValues) -> Result<Option<Value>, anyhow::Error> {
+        match self {
+            Operand::Number(n) => Ok(Some(Value::Number(*n))),
+            Operand::Literal { value } => Ok(Some(match value {
+                serde_json::Value::Number(n) => Value::Number(n.as_f64().unwrap_or(f64::NAN)),
+                serde_json::Value::Bool(b) => Value::Number(*b as u8 as f64),
+                serde_json::Value::String(s) => Value::Text(s.clone()),
+                other => Value::Text(other.to_string()),
+            })),
+            Operand::Column(name) => Ok(row.value(name).cloned()),
+            Operand::Sum { sum } => {
+                let mut total = 0.0;
+                for name in sum {
+                    match row.value(name) {
+                        None => return Ok(None),
+                        Some(Value::Number(n)) => total += n,
+                        Some(Value::Text(text)) => {
+                            return Err(anyhow::anyhow!(
+                                "cannot add non-numeric value '{}' of column {}",
+                                text,
+                                name
+                            ))
+                        }
+                    }
+                }
+                Ok(Some(Value::Number(total)))
+            }
+        }
+    }
+}
+
+impl Comparison {
+    fn columns(&self) -> Vec<&str> {
+        let mut columns = self.left.columns();
+        columns.extend(self.right.columns());
+        columns
+    }
+
+    /// Whether the comparison holds in one row; `None` when an operand is empty.
+    fn evaluate(&self, row: &RowValues) -> Result<Option<bool>, anyhow::Error> {
+        let (Some(left), Some(right)) = (self.left.evaluate(row)?, self.right.evaluate(row)?) else {
+            return Ok(None);
+        };
+        let ordering = match (&left, &right) {
+            (Value::Number(a), Value::Number(b)) => a.partial_cmp(b),
+            (Value::Text(a), Value::Text(b)) => Some(a.cmp(b)),
+            _ => None,
+        };
+        let holds = match self.op {
+            CompareOp::Eq => left == right,
+            CompareOp::Ne => left != right,
+            op => {
+                let ordering = ordering.ok_or_else(|| {
+                    anyhow::anyhow!("cannot order {:?} against {:?} in '{}'", left, right, self)
+                })?;
+                match op {
+                    CompareOp::Lt => ordering.is_lt(),
+                    CompareOp::Le => ordering.is_le(),
+                    CompareOp::Gt => ordering.is_gt(),
+                    _ => ordering.is_ge(),
+                }
+            }
+        };
+        Ok(Some(holds))
+    }
+
+    /// `left=value, right=value` for every column the comparison reads.
+    fn describe_cells(&self, row: &RowValues) -> String {
+        self.columns()
+            .into_iter()
+            .map(|name| format!("{}={:?}", name, row.raw(name)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl Rule {
+    fn severity(&self) -> Severity {
+        match self {
+            Rule::Compare { severity, .. } | Rule::Implies { severity, .. } => *severity,
+        }
+    }
+
+    fn columns(&self) -> Vec<&str> {
+        match self {
+            Rule::Compare { check, .. } => check.columns(),
+            Rule::Implies { condition, then, .. } => {
+                let mut columns = condition.columns();
+                columns.extend(then.columns());
+                columns
+            }
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Rule::Compare { message: Some(m), .. } | Rule::Implies { message: Some(m), .. } => m.clone(),
+            Rule::Compare { check, .. } => check.to_string(),
+            Rule::Implies { condition, then, .. } => format!("if {} then {}", condition, then),
+        }
+    }
+
+    /// Returns a description of the violation if the rule fails in this row.
+    fn check_row(&self, row: &RowValues) -> Result<Option<String>, anyhow::Error> {
+        let failed = match self {
+            Rule::Compare { check, .. } => check
+                .evaluate(row)?
+                .filter(|holds| !holds)
+                .map(|_| check.describe_cells(row)),
+            Rule::Implies { condition, then, .. } => match condition.evaluate(row)? {
+                Some(true) => then.evaluate(row)?.filter(|holds| !holds).map(|_| {
+                    format!("{}, {}", condition.describe_cells(row), then.describe_cells(row))
+                }),
+                _ => None,
+            },
+        };
+        Ok(failed.map(|cells| format!("rule '{}' failed ({})", self.describe(), cells)))
+    }
+}
+
+/// Checks that every column a rule reads is in the schema and is packed.
+pub fn validate_rules(rules: &[Rule], schema: &[ColumnSchema]) -> Result<(), anyhow::Error> {
+    for rule in rules {
+        for name in rule.columns() {
+            match schema.iter().find(|col| col.name() == name) {
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "Rule '{}' refers to column {}, which is not in the schema",
+                        rule.describe(),
+                        name
+                    ))
+                }
+                Some(col) if col.kind() == ColumnKind::Computed => {
+                    return Err(anyhow::anyhow!(
+                        "Rule '{}' refers to computed column {}, which only exists after decoding",
+                        rule.describe(),
+                        name
+                    ))
+                }
+                Some(_) => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+/// One CSV row as rules see it: the normalized cells, for reports, and the stored values.
+struct RowValues<'a> {
+    raw: HashMap<&'a str, String>,
+    values: HashMap<&'a str, Option<Value>>,
+}
+
+impl RowValues<'_> {
+    fn raw(&self, name: &str) -> &str {
+        self.raw.get(name).map_or("", String::as_str)
+    }
+
+    fn value(&self, name: &str) -> Option<&Value> {
+        self.values.get(name).and_then(Option::as_ref)
+    }
+}
+
+/// A column some rule reads, with where to find it in the CSV.
+struct RuleColumn<'a> {
+    name: &'a str,
+    csv_idx: usize,
+    col: &'a ColumnSchema,
+    lookup: Option<HashMap<String, u128>>,
+}
+
+/// The value the encoder stores for a non-empty `raw` cell of `col`.
+fn stored_value(
+    col: &ColumnSchema,
+    raw: &str,
+    lookup: Option<&HashMap<String, u128>>,
+) -> Result<Value, anyhow::Error> {
+    let packed = match col {
+        ColumnSchema::Text { .. }
+        | ColumnSchema::Flags { .. }
+        | ColumnSchema::List { .. }
+        | ColumnSchema::Computed { .. } => {
+            return Ok(match raw.parse::<f64>() {
+                Ok(n) => Value::Number(n),
+                Err(_) => Value::Text(raw.to_string()),
+            })
+        }
+        _ => scalar_value(col, raw, lookup)?,
+    };
+    Ok(match col {
+        ColumnSchema::Int { int_min, .. } => Value::Number((int_min + packed) as f64),
+        ColumnSchema::Sint { .. } => Value::Number(zigzag_decode(packed) as f64),
+        ColumnSchema::Decimal { min, step, precision, .. } => {
+            // Round through the printed form so 3 * 0.1 compares equal to 0.3.
+            let printed = format!("{:.*}", *precision as usize, min + packed as f64 * step);
+            Value::Number(printed.parse().unwrap_or(f64::NAN))
+        }
+        ColumnSchema::Bool { .. } => Value::Number(packed as f64),
+        ColumnSchema::Enum { values, .. } => Value::Text(values[packed as usize].clone()),
+        _ => unreachable!("handled above"),
+    })
+}
+
+/// Runs every rule over the CSV rows (numbered from 1 after the header). Failed
+/// error rules are all reported in one error; failed warning rules are returned.
+pub fn check_rules(
+    headers: &[String],
+    rows: &[Vec<String>],
+    schema: &[ColumnSchema],
+    rules: &[Rule],
+) -> Result<Vec<String>, anyhow::Error> {
+    let header_idx: HashMap<&str, usize> = headers
+        .iter()
+        .enumerate()
+        .map(|(idx, name)| (name.as_str(), idx))
+        .collect();
+    let mut columns: Vec<RuleColumn> = Vec::new();
+    for name in rules.iter().flat_map(Rule::columns) {
+        if columns.iter().any(|seen| seen.name == name) {
+            continue;
+        }
+        let csv_idx = *header_idx
+            .get(name)
+            .ok_or_else(|| anyhow::anyhow!("CSV is missing column {} used by a rule", name))?;
+        let col = schema
+            .iter()
+            .find(|col| col.name() == name)
+            .ok_or_else(|| anyhow::anyhow!("Column {} used by a rule is not in the schema", name))?;
+        columns.push(RuleColumn {
+            name,
+            csv_idx,
+            col,
+            lookup: enum_lookup(col),
+        });
+    }
+
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+    for (row_idx, row) in rows.iter().enumerate() {
+        let mut values = RowValues {
+            raw: HashMap::new(),
+            values: HashMap::new(),
+        };
+        for RuleColumn { name, csv_idx, col, lookup } in &columns {
+            let raw = row.get(*csv_idx).map(|cell| normalize_cell(cell)).unwrap_or_default();
+            let value = if raw.is_empty() {
+                None
+            } else {
+                Some(
+                    stored_value(col, &raw, lookup.as_ref())
+                        .map_err(|e| anyhow::anyhow!("Row {}: {}", row_idx + 1, e))?,
+                )
+            };
+            values.raw.insert(name, raw);
+            values.values.insert(name, value);
+        }
+        for rule in rules {
+            let violation = rule
+                .check_row(&values)
+                .map_err(|e| anyhow::anyhow!("Row {}: {}", row_idx + 1, e))?;
+            if let Some(violation) = violation {
+                let report = format!("Row {}: {}", row_idx + 1, violation);
+                match rule.severity() {
+                    Severity::Error => errors.push(report),
+                    Severity::Warning => warnings.push(report),
+                }
+            }
+        }
+    }
+
+    if !errors.is_empty() {
+        let mut message = errors
+            .iter()
+            .take(MAX_REPORTED_VIOLATIONS)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("\n");
+        if errors.len() > MAX_REPORTED_VIOLATIONS {
+            message.push_str(&format!(
+                "\n... and {} more",
+                errors.len() - MAX_REPORTED_VIOLATIONS
+            ));
+        }
+        return Err(anyhow::anyhow!("CSV breaks schema rules:\n{}", message));
+    }
+    Ok(warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::schema::load_schema;
+
+    fn schema() -> Vec<ColumnSchema> {
+        load_schema(Some(
+            br#"[
+                { "name": "a", "kind": "int", "int_max": 3 },
+                { "name": "b", "kind": "bool" },
+                { "name": "cl", "kind": "enum", "values": ["None", "Deep"],
+                  "aliases": { "no": "None" }, "case_insensitive": true },
+                { "name": "t", "kind": "text", "max_length": 20 }
+            ]"#,
+        ))
+        .unwrap()
+    }
+
+    fn rules(json: &str) -> Vec<Rule> {
+        let rules: Vec<Rule> = serde_json::from_str(json).unwrap();
+        validate_rules(&rules, &schema()).unwrap();
+        rules
+    }
+
+    fn check(rows: &[&str], rules: &[Rule]) -> Result<Vec<String>, anyhow::Error> {
+        let headers: Vec<String> = ["a", "b", "cl", "t"].iter().map(|h| h.to_string()).collect();
+        let rows: Vec<Vec<String>> = rows
+            .iter()
+            .map(|row| row.split(',').map(str::to_string).collect())
+            .collect();
+        check_rules(&headers, &rows, &schema(), rules)
+    }
+
+    const CLIMB_RULE: &str = r#"[{ "kind": "implies",
+        "if": { "left": "a", "op": "==", "right": 1 },
+        "then": { "left": "cl", "op": "!=", "right": { "value": "None" } } }]"#;
+
+    #[test]
+    fn enum_cells_compare_by_canonical_value() {
+        let rules = rules(CLIMB_RULE);
+        for cell in ["None", "none", "NONE", "no", "No"] {
+            let err = check(&[&format!("1,false,{}", cell)], &rules).unwrap_err();
+            assert!(err.to_string().contains("Row 1"), "{}", err);
+        }
+        assert!(check(&["1,false,deep", "0,false,none"], &rules).is_ok());
+    }
+
+    #[test]
+    fn bool_cells_compare_as_one_or_zero() {
+        let rules = rules(
+            r#"[{ "kind": "compare", "left": "b", "op": "==", "right": 1 },
+                { "kind": "compare", "left": { "sum": ["a", "b"] }, "op": "<=", "right": 3 },
+                { "kind": "compare", "left": "b", "op": "==", "right": { "value": true } }]"#,
+        );
+        assert!(check(&["2,YES,None", "1,true,None", "0,1,Deep"], &rules).is_ok());
+        let err = check(&["3,YES,None", "0,F,None"], &rules).unwrap_err().to_string();
+        assert!(err.contains("Row 1: rule 'a + b <= 3'"), "{}", err);
+        assert!(err.contains("Row 2: rule 'b == 1'"), "{}", err);
+    }
+
+    #[test]
+    fn empty_cells_skip_the_rule_and_warnings_do_not_fail() {
+        let rules = rules(
+            r#"[{ "kind": "compare", "left": "a", "op": ">", "right": 0, "severity": "warning" }]"#,
+        );
+        assert_eq!(check(&["1,true,None", ",true,None"], &rules).unwrap(), Vec::<String>::new());
+        let warnings = check(&["0,true,None"], &rules).unwrap();
+        assert_eq!(warnings, vec![r#"Row 1: rule 'a > 0' failed (a="0")"#.to_string()]);
+    }
+
+    #[test]
+    fn cells_are_normalized_like_the_packer_does() {
+        let rules = rules(
+            r#"[{ "kind": "compare", "left": "t", "op": "==", "right": { "value": "two lines" } },
+                { "kind": "compare", "left": "cl", "op": "==", "right": { "value": "Deep" } }]"#,
+        );
+        assert!(check(&["1,true,Dee\rp,two\r\nlines"], &rules).is_ok());
+        let err = check(&["1,true,Deep,two\n\nlines"], &rules).unwrap_err();
+        assert!(err.to_string().contains(r#"t="two  lines""#), "{}", err);
+    }
+
+    #[test]
+    fn invalid_cells_are_reported_with_their_row() {
+        let rules = rules(r#"[{ "kind": "compare", "left": "b", "op": "==", "right": 1 }]"#);
+        let err = check(&["1,maybe,None"], &rules).unwrap_err();
+        assert!(err.to_string().starts_with("Row 1: Invalid boolean value 'maybe'"), "{}", err);
+    }
+}
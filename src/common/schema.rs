@@ -1,5 +1,8 @@
+use crate::common::expression::Expression;
+use crate::common::rules::{validate_rules, Rule};
+use crate::common::schema_registry::{builtin_schema, builtin_schema_names, DEFAULT_SCHEMA_NAME};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::path::Path;
 
 #[derive(Deserialize)]
@@ -9,24 +12,310 @@ enum RawColumnSchema {
     Int {
         name: String,
         bits: Option<u32>,
-        int_max: Option<u64>,
+        #[serde(default, deserialize_with = "optional_wide_int")]
+        int_max: Option<u128>,
+        #[serde(default, deserialize_with = "optional_wide_int")]
+        int_min: Option<u128>,
+        #[serde(default)]
+        nullable: bool,
     },
     #[serde(rename = "enum")]
     Enum {
         name: String,
         bits: Option<u32>,
         values: Vec<String>,
+        #[serde(default)]
+        aliases: BTreeMap<String, String>,
+        #[serde(default)]
+        case_insensitive: bool,
+        #[serde(default)]
+        nullable: bool,
+    },
+    #[serde(rename = "sint")]
+    Sint {
+        name: String,
+        bits: Option<u32>,
+        #[serde(default, deserialize_with = "optional_wide_int")]
+        int_max: Option<u128>,
+        #[serde(default)]
+        nullable: bool,
+    },
+    #[serde(rename = "decimal")]
+    Decimal {
+        name: String,
+        min: f64,
+        max: f64,
+        step: f64,
+        precision: Option<u32>,
+        #[serde(default)]
+        nullable: bool,
+    },
+    #[serde(rename = "bool")]
+    Bool {
+        name: String,
+        true_values: Option<Vec<String>>,
+        false_values: Option<Vec<String>>,
+        true_output: Option<String>,
+        false_output: Option<String>,
+        #[serde(default)]
+        nullable: bool,
+    },
+    #[serde(rename = "text")]
+    Text {
+        name: String,
+        max_length: u32,
+        #[serde(default)]
+        nullable: bool,
+    },
+    #[serde(rename = "flags")]
+    Flags {
+        name: String,
+        options: Vec<String>,
+        delimiter: Option<String>,
+        #[serde(default)]
+        nullable: bool,
+    },
+    #[serde(rename = "computed")]
+    Computed {
+        name: String,
+        expression: String,
+        precision: Option<u32>,
+    },
+    /// `element` is an int, enum or decimal column definition without a name.
+    #[serde(rename = "list")]
+    List {
+        name: String,
+        element: serde_json::Value,
+        max_length: u32,
+        delimiter: Option<String>,
+    },
+    /// Sugar for a 0-bit column: a non-negative integer becomes an int column with
+    /// `int_min == int_max`, a string a single-value enum.
+    #[serde(rename = "constant")]
+    Constant {
+        name: String,
+        value: serde_json::Value,
+    },
+    /// Expands `columns` once per combination of `vars`, replacing `{var}` in every
+    /// string of the column templates. The first var varies slowest.
+    #[serde(rename = "group")]
+    Group {
+        vars: Vec<RawGroupVar>,
+        columns: Vec<serde_json::Value>,
     },
 }
 
+/// An int bound written as a JSON number, or as a decimal string for values that JSON
+/// numbers can't carry exactly (anything past `u64`, or 2^53 for JavaScript callers).
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawWideInt {
+    Number(u64),
+    Text(String),
+}
+
+fn wide_int<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+    let raw = RawWideInt::deserialize(deserializer).map_err(|_| {
+        serde::de::Error::custom("int_max/int_min must be an integer or decimal string")
+    })?;
+    match raw {
+        RawWideInt::Number(n) => Ok(n.into()),
+        RawWideInt::Text(s) => s.trim().parse().map_err(|_| {
+            serde::de::Error::custom(format!("'{}' is not an integer in 0..2^128", s))
+        }),
+    }
+}
+
+fn optional_wide_int<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<u128>, D::Error> {
+    wide_int(deserializer).map(Some)
+}
+
+/// Writes an int bound the way `wide_int` reads it back: a number while it fits in
+/// `u64`, a decimal string past that.
+fn serialize_wide_int<S: serde::Serializer>(value: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+    match u64::try_from(*value) {
+        Ok(n) => serializer.serialize_u64(n),
+        Err(_) => serializer.collect_str(value),
+    }
+}
+
+#[derive(Deserialize)]
+struct RawGroupVar {
+    name: String,
+    values: Vec<String>,
+}
+
+/// Replaces every group in `raw` with its concrete columns. Groups cannot be nested,
+/// and each needs at least one var, one value per var and one column.
+fn expand_groups(raw: Vec<RawColumnSchema>) -> Result<Vec<RawColumnSchema>, anyhow::Error> {
+    let mut expanded = Vec::with_capacity(raw.len());
+    for col in raw {
+        let RawColumnSchema::Group { vars, columns } = col else {
+            expanded.push(col);
+            continue;
+        };
+
+        if vars.is_empty() || columns.is_empty() {
+            return Err(anyhow::anyhow!("group needs at least one var and one column"));
+        }
+        let mut bindings: Vec<Vec<(&str, &str)>> = vec![Vec::new()];
+        for var in &vars {
+            if var.name.is_empty() {
+                return Err(anyhow::anyhow!("group var names cannot be empty"));
+            }
+            if var.values.is_empty() {
+                return Err(anyhow::anyhow!("group var {} has no values", var.name));
+            }
+            bindings = bindings
+                .iter()
+                .flat_map(|binding| {
+                    var.values.iter().map(move |value| {
+                        let mut next = binding.clone();
+                        next.push((var.name.as_str(), value.as_str()));
+                        next
+                    })
+                })
+                .collect();
+        }
+
+        for binding in &bindings {
+            let concrete = columns
+                .iter()
+                .map(|template| {
+                    serde_json::from_value(substitute_vars(template.clone(), binding))
+                        .map_err(|e| anyhow::anyhow!("Invalid column in group: {}", e))
+                })
+                .collect::<Result<Vec<RawColumnSchema>, _>>()?;
+            if concrete.iter().any(|col| matches!(col, RawColumnSchema::Group { .. })) {
+                return Err(anyhow::anyhow!("groups cannot be nested"));
+            }
+            expanded.extend(concrete);
+        }
+    }
+    Ok(expanded)
+}
+
+fn substitute_vars(value: serde_json::Value, binding: &[(&str, &str)]) -> serde_json::Value {
+    use serde_json::Value;
+    match value {
+        Value::String(mut s) => {
+            for (name, replacement) in binding {
+                s = s.replace(&format!("{{{}}}", name), replacement);
+            }
+            Value::String(s)
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(|v| substitute_vars(v, binding)).collect()),
+        Value::Object(fields) => Value::Object(
+            fields
+                .into_iter()
+                .map(|(k, v)| (k, substitute_vars(v, binding)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+/// Spellings a bool column accepts when the schema does not list its own.
+const DEFAULT_TRUE_VALUES: [&str; 5] = ["true", "yes", "y", "t", "1"];
+const DEFAULT_FALSE_VALUES: [&str; 5] = ["false", "no", "n", "f", "0"];
+
+/// Most decimal places a decimal column prints.
+const MAX_DECIMAL_PRECISION: u32 = 9;
+
+/// Widest int or sint column; packed values are held as `u128`.
+pub const MAX_INT_BITS: u32 = 128;
+
+/// Index of the last step in `min..=max` (`max` is rounded down onto the grid).
+pub fn decimal_max_index(min: f64, max: f64, step: f64) -> u64 {
+    ((max - min) / step + 1e-9).floor() as u64
+}
+
+/// Whether `max` lies a whole number of `step`s above `min`.
+fn is_whole_steps(min: f64, max: f64, step: f64) -> bool {
+    let steps = (max - min) / step;
+    (steps - steps.round()).abs() <= 1e-6
+}
+
+/// Decimal places needed to print `value` exactly, e.g. 2 for 0.25.
+fn decimal_places(value: f64) -> u32 {
+    (0..MAX_DECIMAL_PRECISION)
+        .find(|&p| {
+            let scaled = value * 10f64.powi(p as i32);
+            (scaled - scaled.round()).abs() < 1e-9 * scaled.max(1.0)
+        })
+        .unwrap_or(MAX_DECIMAL_PRECISION)
+}
+
+/// Bits needed to store offsets `0..=span`.
+fn int_bits_needed(span: u128) -> u32 {
+    u128::BITS - span.leading_zeros()
+}
+
+/// Largest offset that fits in `bits` bits.
+fn int_max_for_bits(bits: u32) -> u128 {
+    if bits == 0 {
+        0
+    } else {
+        u128::MAX >> (u128::BITS - bits.min(MAX_INT_BITS))
+    }
+}
+
+/// Width of a list column's per-row length.
+pub fn list_length_bits(max_length: u32) -> u32 {
+    int_bits_needed(max_length.into())
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+fn is_zero(value: &u128) -> bool {
+    *value == 0
+}
+
+/// Bits needed for the zigzag codes of `-int_max..=int_max` (the largest code is `2 * int_max`).
+fn sint_bits_needed(int_max: u128) -> u32 {
+    u128::BITS - (int_max * 2).leading_zeros()
+}
+
+/// Largest magnitude whose zigzag codes all fit in `bits` bits.
+fn sint_max_for_bits(bits: u32) -> u128 {
+    if bits == 0 {
+        0
+    } else {
+        (1u128 << (bits.min(MAX_INT_BITS) - 1)) - 1
+    }
+}
+
 fn resolve_raw_schema(
     raw: Vec<RawColumnSchema>,
     warnings: &mut Vec<String>,
 ) -> Result<Vec<ColumnSchema>, anyhow::Error> {
+    let raw = expand_groups(raw)?;
     let mut resolved = Vec::with_capacity(raw.len());
     for col in raw {
         match col {
-            RawColumnSchema::Int { name, bits, int_max } => {
+            RawColumnSchema::Int { name, bits, int_max, int_min, nullable } => {
+                if let Some(b) = bits.filter(|&b| b > MAX_INT_BITS) {
+                    return Err(anyhow::anyhow!(
+                        "int column '{}': bits={} exceeds {}",
+                        name,
+                        b,
+                        MAX_INT_BITS
+                    ));
+                }
+                let int_min = int_min.unwrap_or(0);
+                if let Some(m) = int_max {
+                    if m < int_min {
+                        return Err(anyhow::anyhow!(
+                            "int column '{}': int_max {} is below int_min {}",
+                            name,
+                            m,
+                            int_min
+                        ));
+                    }
+                }
+                // Bits cover the span above int_min; values are packed as offsets from it.
                 let (resolved_bits, resolved_int_max) = match (bits, int_max) {
                     (None, None) => {
                         return Err(anyhow::anyhow!(
@@ -35,17 +324,9 @@ fn resolve_raw_schema(
                         ));
                     }
                     (Some(b), Some(m)) => {
-                        let bits_needed = if m == 0 {
-                            0u32
-                        } else {
-                            ((m as f64 + 1.0).log2().ceil()) as u32
-                        };
-                        let effective_bits = b.min(bits_needed);
-                        let effective_int_max = m.min(if effective_bits > 0 {
-                            (1u64 << effective_bits) - 1
-                        } else {
-                            0
-                        });
+                        let span = m - int_min;
+                        let effective_bits = b.min(int_bits_needed(span));
+                        let effective_int_max = int_min + span.min(int_max_for_bits(effective_bits));
                         warnings.push(format!(
                             "Column '{}': both 'bits' and 'int_max' provided; \
                              using bits={}, int_max={}",
@@ -54,25 +335,27 @@ fn resolve_raw_schema(
                         (effective_bits, effective_int_max)
                     }
                     (Some(b), None) => {
-                        let m = if b > 0 { (1u64 << b) - 1 } else { 0 };
-                        (b, m)
-                    }
-                    (None, Some(m)) => {
-                        let b = if m == 0 {
-                            0u32
-                        } else {
-                            ((m as f64 + 1.0).log2().ceil()) as u32
-                        };
+                        let m = int_min.checked_add(int_max_for_bits(b)).ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "int column '{}': int_min {} plus {} bits overflows 128 bits",
+                                name,
+                                int_min,
+                                b
+                            )
+                        })?;
                         (b, m)
                     }
+                    (None, Some(m)) => (int_bits_needed(m - int_min), m),
                 };
                 resolved.push(ColumnSchema::Int {
                     name,
                     bits: resolved_bits,
                     int_max: resolved_int_max,
+                    int_min,
+                    nullable,
                 });
             }
-            RawColumnSchema::Enum { name, bits, values } => {
+            RawColumnSchema::Enum { name, bits, values, aliases, case_insensitive, nullable } => {
                 let count = values.len();
                 let bits_needed = if count <= 1 {
                     0u32
@@ -96,8 +379,180 @@ fn resolve_raw_schema(
                     name,
                     bits: resolved_bits,
                     values,
+                    aliases,
+                    case_insensitive,
+                    nullable,
                 });
             }
+            RawColumnSchema::Sint { name, bits, int_max, nullable } => {
+                if let Some(b) = bits {
+                    if b > MAX_INT_BITS {
+                        return Err(anyhow::anyhow!(
+                            "sint column '{}': bits={} exceeds {}",
+                            name,
+                            b,
+                            MAX_INT_BITS
+                        ));
+                    }
+                    if b == 1 {
+                        return Err(anyhow::anyhow!(
+                            "sint column '{}': bits=1 can only hold 0; use at least 2 bits",
+                            name
+                        ));
+                    }
+                }
+                if let Some(m) = int_max {
+                    if m > i128::MAX as u128 {
+                        return Err(anyhow::anyhow!(
+                            "sint column '{}': int_max {} exceeds the signed 128-bit range",
+                            name,
+                            m
+                        ));
+                    }
+                }
+                let (resolved_bits, resolved_int_max) = match (bits, int_max) {
+                    (None, None) => {
+                        return Err(anyhow::anyhow!(
+                            "sint column '{}': must provide 'bits' or 'int_max'",
+                            name
+                        ));
+                    }
+                    (Some(b), Some(m)) => {
+                        let effective_bits = b.min(sint_bits_needed(m));
+                        let effective_int_max = m.min(sint_max_for_bits(effective_bits));
+                        warnings.push(format!(
+                            "Column '{}': both 'bits' and 'int_max' provided; \
+                             using bits={}, int_max={}",
+                            name, effective_bits, effective_int_max
+                        ));
+                        (effective_bits, effective_int_max)
+                    }
+                    (Some(b), None) => (b, sint_max_for_bits(b)),
+                    (None, Some(m)) => (sint_bits_needed(m), m),
+                };
+                resolved.push(ColumnSchema::Sint {
+                    name,
+                    bits: resolved_bits,
+                    int_max: resolved_int_max,
+                    nullable,
+                });
+            }
+            RawColumnSchema::Decimal { name, min, max, step, precision, nullable } => {
+                if !(min.is_finite() && max.is_finite() && step.is_finite()) || step <= 0.0 || max < min {
+                    return Err(anyhow::anyhow!(
+                        "decimal column '{}': need finite min <= max and step > 0",
+                        name
+                    ));
+                }
+                let steps = (max - min) / step;
+                if steps >= (1u64 << 53) as f64 {
+                    return Err(anyhow::anyhow!(
+                        "decimal column '{}': too many steps between min and max",
+                        name
+                    ));
+                }
+                if !is_whole_steps(min, max, step) {
+                    return Err(anyhow::anyhow!(
+                        "decimal column '{}': max {} is not a whole number of steps from min (nearest below is {:.*})",
+                        name,
+                        max,
+                        decimal_places(step).max(decimal_places(min.abs())) as usize,
+                        min + decimal_max_index(min, max, step) as f64 * step
+                    ));
+                }
+                resolved.push(ColumnSchema::Decimal {
+                    bits: int_bits_needed(decimal_max_index(min, max, step).into()),
+                    precision: precision.unwrap_or_else(|| decimal_places(step).max(decimal_places(min.abs()))),
+                    name,
+                    min,
+                    max,
+                    step,
+                    nullable,
+                });
+            }
+            RawColumnSchema::Bool { name, true_values, false_values, true_output, false_output, nullable } => {
+                let spellings = |given: Option<Vec<String>>, defaults: [&str; 5]| {
+                    given.unwrap_or_else(|| defaults.iter().map(|s| s.to_string()).collect())
+                };
+                resolved.push(ColumnSchema::Bool {
+                    name,
+                    true_values: spellings(true_values, DEFAULT_TRUE_VALUES),
+                    false_values: spellings(false_values, DEFAULT_FALSE_VALUES),
+                    true_output: true_output.unwrap_or_else(|| "true".to_string()),
+                    false_output: false_output.unwrap_or_else(|| "false".to_string()),
+                    nullable,
+                });
+            }
+            RawColumnSchema::Text { name, max_length, nullable } => {
+                resolved.push(ColumnSchema::Text { name, max_length, nullable });
+            }
+            RawColumnSchema::Flags { name, options, delimiter, nullable } => {
+                resolved.push(ColumnSchema::Flags {
+                    name,
+                    bits: options.len() as u32,
+                    options,
+                    delimiter: delimiter.unwrap_or_else(|| "|".to_string()),
+                    nullable,
+                });
+            }
+            RawColumnSchema::Computed { name, expression, precision } => {
+                resolved.push(ColumnSchema::Computed { name, expression, precision });
+            }
+            RawColumnSchema::List { name, element, max_length, delimiter } => {
+                let serde_json::Value::Object(mut element) = element else {
+                    return Err(anyhow::anyhow!("list column '{}': element must be an object", name));
+                };
+                if element.contains_key("name") {
+                    return Err(anyhow::anyhow!(
+                        "list column '{}': element takes its name from the list",
+                        name
+                    ));
+                }
+                element.insert("name".to_string(), serde_json::Value::String(name.clone()));
+                let raw_element = serde_json::from_value(serde_json::Value::Object(element))
+                    .map_err(|e| anyhow::anyhow!("list column '{}': invalid element: {}", name, e))?;
+                let mut elements = resolve_raw_schema(vec![raw_element], warnings)?;
+                if elements.len() != 1 {
+                    return Err(anyhow::anyhow!("list column '{}': element must be a single column", name));
+                }
+                resolved.push(ColumnSchema::List {
+                    bits: list_length_bits(max_length),
+                    element: Box::new(elements.remove(0)),
+                    name,
+                    max_length,
+                    delimiter: delimiter.unwrap_or_else(|| "|".to_string()),
+                });
+            }
+            RawColumnSchema::Constant { name, value } => match value {
+                serde_json::Value::Number(n) if n.is_u64() => {
+                    let value = n.as_u64().unwrap_or_default() as u128;
+                    resolved.push(ColumnSchema::Int {
+                        name,
+                        bits: 0,
+                        int_max: value,
+                        int_min: value,
+                        nullable: false,
+                    });
+                }
+                serde_json::Value::String(value) => {
+                    resolved.push(ColumnSchema::Enum {
+                        name,
+                        bits: 0,
+                        values: vec![value],
+                        aliases: BTreeMap::new(),
+                        case_insensitive: false,
+                        nullable: false,
+                    });
+                }
+                other => {
+                    return Err(anyhow::anyhow!(
+                        "constant column '{}': value must be a string or a non-negative integer, got {}",
+                        name,
+                        other
+                    ));
+                }
+            },
+            RawColumnSchema::Group { .. } => unreachable!("groups are expanded above"),
         }
     }
     Ok(resolved)
@@ -107,22 +562,125 @@ fn resolve_raw_schema(
 pub enum ColumnKind {
     Int,
     Enum,
+    Sint,
+    Decimal,
+    Bool,
+    Text,
+    Flags,
+    Computed,
+    List,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "kind")]
 pub enum ColumnSchema {
+    /// Unsigned integer in `int_min..=int_max`, packed as the offset from `int_min`.
     #[serde(rename = "int")]
     Int {
         name: String,
         bits: u32,
-        int_max: u64,
+        #[serde(serialize_with = "serialize_wide_int", deserialize_with = "wide_int")]
+        int_max: u128,
+        #[serde(
+            default,
+            skip_serializing_if = "is_zero",
+            serialize_with = "serialize_wide_int",
+            deserialize_with = "wide_int"
+        )]
+        int_min: u128,
+        #[serde(default, skip_serializing_if = "is_false")]
+        nullable: bool,
     },
+    /// One of `values`, packed as its index. On encode, `aliases` maps alternative
+    /// spellings to a canonical value and `case_insensitive` ignores ASCII case.
     #[serde(rename = "enum")]
     Enum {
         name: String,
         bits: u32,
         values: Vec<String>,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        aliases: BTreeMap<String, String>,
+        #[serde(default, skip_serializing_if = "is_false")]
+        case_insensitive: bool,
+        #[serde(default, skip_serializing_if = "is_false")]
+        nullable: bool,
+    },
+    /// Signed integer in `-int_max..=int_max`, zigzag-encoded so small magnitudes
+    /// of either sign stay small.
+    #[serde(rename = "sint")]
+    Sint {
+        name: String,
+        bits: u32,
+        #[serde(serialize_with = "serialize_wide_int", deserialize_with = "wide_int")]
+        int_max: u128,
+        #[serde(default, skip_serializing_if = "is_false")]
+        nullable: bool,
+    },
+    /// Fixed-point number in `min..=max`, quantized to the nearest `step` and packed
+    /// as the step index; printed with `precision` decimal places.
+    #[serde(rename = "decimal")]
+    Decimal {
+        name: String,
+        bits: u32,
+        min: f64,
+        max: f64,
+        step: f64,
+        precision: u32,
+        #[serde(default, skip_serializing_if = "is_false")]
+        nullable: bool,
+    },
+    /// Single-bit flag. Any of `true_values` / `false_values` is accepted on encode
+    /// (ignoring ASCII case); decoding prints `true_output` / `false_output`.
+    #[serde(rename = "bool")]
+    Bool {
+        name: String,
+        true_values: Vec<String>,
+        false_values: Vec<String>,
+        true_output: String,
+        false_output: String,
+        #[serde(default, skip_serializing_if = "is_false")]
+        nullable: bool,
+    },
+    /// Free text of at most `max_length` characters. Values live in the payload's
+    /// string table; the column stores indices whose width depends on the table size,
+    /// so `bits()` is 0.
+    #[serde(rename = "text")]
+    Text {
+        name: String,
+        max_length: u32,
+        #[serde(default, skip_serializing_if = "is_false")]
+        nullable: bool,
+    },
+    /// Any subset of `options`, written as a `delimiter`-separated list and packed
+    /// as one bit per option (bit `i` for `options[i]`).
+    #[serde(rename = "flags")]
+    Flags {
+        name: String,
+        bits: u32,
+        options: Vec<String>,
+        delimiter: String,
+        #[serde(default, skip_serializing_if = "is_false")]
+        nullable: bool,
+    },
+    /// Up to `max_length` values of `element` (int, enum or decimal), written as a
+    /// `delimiter`-separated list. Packed as a `bits`-wide length per row, followed by
+    /// every row's elements as one stream.
+    #[serde(rename = "list")]
+    List {
+        name: String,
+        bits: u32,
+        max_length: u32,
+        delimiter: String,
+        element: Box<ColumnSchema>,
+    },
+    /// Derived from earlier columns by `expression` (see `Expression`). Never packed;
+    /// the decoder fills it in, printing `precision` decimal places when set.
+    #[serde(rename = "computed")]
+    Computed {
+        name: String,
+        expression: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        precision: Option<u32>,
     },
 }
 
@@ -131,6 +689,13 @@ impl ColumnSchema {
         match self {
             ColumnSchema::Int { name, .. } => name,
             ColumnSchema::Enum { name, .. } => name,
+            ColumnSchema::Sint { name, .. } => name,
+            ColumnSchema::Decimal { name, .. } => name,
+            ColumnSchema::Bool { name, .. } => name,
+            ColumnSchema::Text { name, .. } => name,
+            ColumnSchema::Flags { name, .. } => name,
+            ColumnSchema::Computed { name, .. } => name,
+            ColumnSchema::List { name, .. } => name,
         }
     }
 
@@ -138,6 +703,13 @@ impl ColumnSchema {
         match self {
             ColumnSchema::Int { .. } => ColumnKind::Int,
             ColumnSchema::Enum { .. } => ColumnKind::Enum,
+            ColumnSchema::Sint { .. } => ColumnKind::Sint,
+            ColumnSchema::Decimal { .. } => ColumnKind::Decimal,
+            ColumnSchema::Bool { .. } => ColumnKind::Bool,
+            ColumnSchema::Text { .. } => ColumnKind::Text,
+            ColumnSchema::Flags { .. } => ColumnKind::Flags,
+            ColumnSchema::Computed { .. } => ColumnKind::Computed,
+            ColumnSchema::List { .. } => ColumnKind::List,
         }
     }
 
@@ -145,13 +717,41 @@ impl ColumnSchema {
         match self {
             ColumnSchema::Int { bits, .. } => *bits,
             ColumnSchema::Enum { bits, .. } => *bits,
+            ColumnSchema::Sint { bits, .. } => *bits,
+            ColumnSchema::Decimal { bits, .. } => *bits,
+            ColumnSchema::Bool { .. } => 1,
+            ColumnSchema::Text { .. } => 0,
+            ColumnSchema::Flags { bits, .. } => *bits,
+            ColumnSchema::Computed { .. } => 0,
+            ColumnSchema::List { bits, .. } => *bits,
         }
     }
 
-    pub fn int_max(&self) -> Option<u64> {
+    pub fn int_max(&self) -> Option<u128> {
         match self {
             ColumnSchema::Int { int_max, .. } => Some(*int_max),
             ColumnSchema::Enum { .. } => None,
+            ColumnSchema::Sint { int_max, .. } => Some(*int_max),
+            ColumnSchema::Decimal { .. } => None,
+            ColumnSchema::Bool { .. } => None,
+            ColumnSchema::Text { .. } => None,
+            ColumnSchema::Flags { .. } => None,
+            ColumnSchema::Computed { .. } => None,
+            ColumnSchema::List { .. } => None,
+        }
+    }
+
+    pub fn int_min(&self) -> Option<u128> {
+        match self {
+            ColumnSchema::Int { int_min, .. } => Some(*int_min),
+            ColumnSchema::Enum { .. } => None,
+            ColumnSchema::Sint { .. } => None,
+            ColumnSchema::Decimal { .. } => None,
+            ColumnSchema::Bool { .. } => None,
+            ColumnSchema::Text { .. } => None,
+            ColumnSchema::Flags { .. } => None,
+            ColumnSchema::Computed { .. } => None,
+            ColumnSchema::List { .. } => None,
         }
     }
 
@@ -159,206 +759,166 @@ impl ColumnSchema {
         match self {
             ColumnSchema::Int { .. } => None,
             ColumnSchema::Enum { values, .. } => Some(values),
+            ColumnSchema::Sint { .. } => None,
+            ColumnSchema::Decimal { .. } => None,
+            ColumnSchema::Bool { .. } => None,
+            ColumnSchema::Text { .. } => None,
+            ColumnSchema::Flags { .. } => None,
+            ColumnSchema::Computed { .. } => None,
+            ColumnSchema::List { .. } => None,
         }
     }
+
+    /// Whether empty cells are allowed; they are packed as a cleared presence bit.
+    pub fn nullable(&self) -> bool {
+        match self {
+            ColumnSchema::Int { nullable, .. }
+            | ColumnSchema::Enum { nullable, .. }
+            | ColumnSchema::Sint { nullable, .. }
+            | ColumnSchema::Decimal { nullable, .. }
+            | ColumnSchema::Bool { nullable, .. }
+            | ColumnSchema::Text { nullable, .. }
+            | ColumnSchema::Flags { nullable, .. } => *nullable,
+            ColumnSchema::Computed { .. } | ColumnSchema::List { .. } => false,
+        }
+    }
+}
+
+/// CRC32 of the canonical JSON of a resolved schema. Packed payloads record it so a
+/// decoder handed a different schema fails instead of misreading the bitplanes.
+pub fn schema_fingerprint(schema: &[ColumnSchema]) -> Result<u32, anyhow::Error> {
+    let canonical = serde_json::to_vec(schema)?;
+    Ok(crc32fast::hash(&canonical))
 }
 
+/// The built-in schema used when none is given (see `schema_registry`).
 pub fn get_default_schema() -> Vec<ColumnSchema> {
-    vec![
-        ColumnSchema::Enum {
-            name: "ScoutName".to_string(),
-            bits: 2,
-            values: vec!["Jude".to_string(), "Dillon".to_string(), "".to_string(), "".to_string()],
-        },
-        ColumnSchema::Int {
-            name: "MatchNumber".to_string(),
-            bits: 8,
-            int_max: 200,
-        },
-        ColumnSchema::Int {
-            name: "TeamNumber".to_string(),
-            bits: 14,
-            int_max: 16383,
-        },
-        ColumnSchema::Int {
-            name: "Mobility".to_string(),
-            bits: 1,
-            int_max: 1,
-        },
-        ColumnSchema::Int {
-            name: "AutonL1Attempted".to_string(),
-            bits: 4,
-            int_max: 12,
-        },
-        ColumnSchema::Int {
-            name: "AutonL1Scored".to_string(),
-            bits: 4,
-            int_max: 12,
-        },
-        ColumnSchema::Int {
-            name: "AutonL2Attempted".to_string(),
-            bits: 4,
-            int_max: 12,
-        },
-        ColumnSchema::Int {
-            name: "AutonL2Scored".to_string(),
-            bits: 4,
-            int_max: 12,
-        },
-        ColumnSchema::Int {
-            name: "AutonL3Attempted".to_string(),
-            bits: 4,
-            int_max: 12,
-        },
-        ColumnSchema::Int {
-            name: "AutonL3Scored".to_string(),
-            bits: 4,
-            int_max: 12,
-        },
-        ColumnSchema::Int {
-            name: "AutonL4Attempted".to_string(),
-            bits: 4,
-            int_max: 12,
-        },
-        ColumnSchema::Int {
-            name: "AutonL4Scored".to_string(),
-            bits: 4,
-            int_max: 12,
-        },
-        ColumnSchema::Int {
-            name: "AutonBargeAttempted".to_string(),
-            bits: 0,
-            int_max: 0,
-        },
-        ColumnSchema::Int {
-            name: "AutonBargeScored".to_string(),
-            bits: 0,
-            int_max: 0,
-        },
-        ColumnSchema::Int {
-            name: "AutonProcessorAttempted".to_string(),
-            bits: 0,
-            int_max: 0,
-        },
-        ColumnSchema::Int {
-            name: "AutonProcessorScored".to_string(),
-            bits: 0,
-            int_max: 0,
-        },
-        ColumnSchema::Int {
-            name: "AutonAlgaeRemoved".to_string(),
-            bits: 0,
-            int_max: 0,
-        },
-        ColumnSchema::Int {
-            name: "TeleopL1Attempted".to_string(),
-            bits: 4,
-            int_max: 12,
-        },
-        ColumnSchema::Int {
-            name: "TeleopL1Scored".to_string(),
-            bits: 4,
-            int_max: 12,
-        },
-        ColumnSchema::Int {
-            name: "TeleopL2Attempted".to_string(),
-            bits: 4,
-            int_max: 12,
-        },
-        ColumnSchema::Int {
-            name: "TeleopL2Scored".to_string(),
-            bits: 4,
-            int_max: 12,
-        },
-        ColumnSchema::Int {
-            name: "TeleopL3Attempted".to_string(),
-            bits: 4,
-            int_max: 12,
-        },
-        ColumnSchema::Int {
-            name: "TeleopL3Scored".to_string(),
-            bits: 4,
-            int_max: 12,
-        },
-        ColumnSchema::Int {
-            name: "TeleopL4Attempted".to_string(),
-            bits: 4,
-            int_max: 12,
-        },
-        ColumnSchema::Int {
-            name: "TeleopL4Scored".to_string(),
-            bits: 4,
-            int_max: 12,
-        },
-        ColumnSchema::Int {
-            name: "TeleopBargeAttempted".to_string(),
-            bits: 3,
-            int_max: 7,
-        },
-        ColumnSchema::Int {
-            name: "TeleopBargeScored".to_string(),
-            bits: 3,
-            int_max: 7,
-        },
-        ColumnSchema::Int {
-            name: "TeleopProcessorAttempted".to_string(),
-            bits: 3,
-            int_max: 7,
-        },
-        ColumnSchema::Int {
-            name: "TeleopProcessorScored".to_string(),
-            bits: 3,
-            int_max: 7,
-        },
-        ColumnSchema::Int {
-            name: "TeleopAlgaeRemoved".to_string(),
-            bits: 3,
-            int_max: 7,
-        },
-        ColumnSchema::Int {
-            name: "ClimbSuccessful".to_string(),
-            bits: 1,
-            int_max: 1,
-        },
-        ColumnSchema::Enum {
-            name: "Climb".to_string(),
-            bits: 2,
-            values: vec!["None".to_string(), "Shallow".to_string(), "Deep".to_string(), "Park".to_string()],
-        },
-        ColumnSchema::Enum {
-            name: "Breakdown".to_string(),
-            bits: 1,
-            values: vec!["False".to_string(), "True".to_string()],
-        },
-        ColumnSchema::Enum {
-            name: "DefenseDescription".to_string(),
-            bits: 0,
-            values: vec!["".to_string()],
-        },
-        ColumnSchema::Enum {
-            name: "Notes".to_string(),
-            bits: 1,
-            values: vec!["".to_string(), "Some note".to_string()],
-        },
-    ]
+    builtin_schema(DEFAULT_SCHEMA_NAME).expect("default schema is registered")
 }
 
 pub fn load_schema_from_json(path: &Path) -> Result<Vec<ColumnSchema>, anyhow::Error> {
+    let mut warnings = Vec::new();
+    let document = resolve_schema_file(path, &mut warnings, 0)?;
+    for w in &warnings {
+        eprintln!("[schema warning] {}", w);
+    }
+    validate_schema(&document.columns)?;
+    validate_rules(&document.rules, &document.columns)?;
+    Ok(document.columns)
+}
+
+/// A resolved schema together with the cross-column rules its JSON declares.
+#[derive(Debug, Clone)]
+pub struct SchemaDocument {
+    pub columns: Vec<ColumnSchema>,
+    pub rules: Vec<Rule>,
+}
+
+/// How many `extends` a schema may chain through before it is treated as a cycle.
+const MAX_EXTENDS_DEPTH: usize = 16;
+
+/// Object form of a schema, which can start from another one and edit its columns by
+/// name, and can declare rules.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawSchemaObject {
+    /// `"default"` or a built-in schema name, a path to a schema file, or an inline
+    /// schema. Without it the object starts from no columns.
+    extends: Option<serde_json::Value>,
+    #[serde(default)]
+    remove: Vec<String>,
+    /// Each replaces the base column of the same name in place, or is appended.
+    #[serde(default)]
+    columns: Vec<RawColumnSchema>,
+    /// Added to any rules inherited from the base.
+    #[serde(default)]
+    rules: Vec<Rule>,
+}
+
+fn resolve_schema_file(
+    path: &Path,
+    warnings: &mut Vec<String>,
+    depth: usize,
+) -> Result<SchemaDocument, anyhow::Error> {
     if !path.exists() {
         return Err(anyhow::anyhow!("Schema file not found: {}", path.display()));
     }
 
     let content = std::fs::read_to_string(path)?;
-    let raw: Vec<RawColumnSchema> = serde_json::from_str(&content)
-        .map_err(|e| anyhow::anyhow!("Invalid JSON in schema file: {}", e))?;
+    let value: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| anyhow::anyhow!("Invalid JSON in schema file {}: {}", path.display(), e))?;
+    resolve_schema_value(value, path.parent(), warnings, depth)
+}
 
-    let mut warnings = Vec::new();
-    let schema = resolve_raw_schema(raw, &mut warnings)?;
-    for w in &warnings {
-        eprintln!("[schema warning] {}", w);
+/// Resolves a schema document: an array of columns, the name of a built-in schema,
+/// or an object (see `RawSchemaObject`). Relative `extends` paths are looked up in
+/// `base_dir`, or the working directory when there is none.
+fn resolve_schema_value(
+    value: serde_json::Value,
+    base_dir: Option<&Path>,
+    warnings: &mut Vec<String>,
+    depth: usize,
+) -> Result<SchemaDocument, anyhow::Error> {
+    if depth > MAX_EXTENDS_DEPTH {
+        return Err(anyhow::anyhow!(
+            "Schema extends more than {} levels deep; is there a cycle?",
+            MAX_EXTENDS_DEPTH
+        ));
     }
-    validate_schema(&schema)?;
-    Ok(schema)
+
+    if let serde_json::Value::String(name) = &value {
+        return Ok(SchemaDocument {
+            columns: builtin_schema(name)?,
+            rules: Vec::new(),
+        });
+    }
+    if value.is_array() {
+        let raw: Vec<RawColumnSchema> =
+            serde_json::from_value(value).map_err(|e| anyhow::anyhow!("Invalid schema: {}", e))?;
+        return Ok(SchemaDocument {
+            columns: resolve_raw_schema(raw, warnings)?,
+            rules: Vec::new(),
+        });
+    }
+
+    let object: RawSchemaObject =
+        serde_json::from_value(value).map_err(|e| anyhow::anyhow!("Invalid schema: {}", e))?;
+    let mut document = match object.extends {
+        None => SchemaDocument {
+            columns: Vec::new(),
+            rules: Vec::new(),
+        },
+        Some(serde_json::Value::String(base)) if base == "default" => SchemaDocument {
+            columns: get_default_schema(),
+            rules: Vec::new(),
+        },
+        Some(serde_json::Value::String(base)) if !builtin_schema_names().contains(&base.as_str()) => {
+            let base_path = match base_dir {
+                Some(dir) => dir.join(&base),
+                None => base.into(),
+            };
+            resolve_schema_file(&base_path, warnings, depth + 1)?
+        }
+        Some(base) => resolve_schema_value(base, base_dir, warnings, depth + 1)?,
+    };
+
+    let schema = &mut document.columns;
+    for name in &object.remove {
+        let before = schema.len();
+        schema.retain(|col| col.name() != name);
+        if schema.len() == before {
+            return Err(anyhow::anyhow!("Cannot remove column {}: not in the base schema", name));
+        }
+    }
+
+    for col in resolve_raw_schema(object.columns, warnings)? {
+        match schema.iter_mut().find(|base| base.name() == col.name()) {
+            Some(base) => *base = col,
+            None => schema.push(col),
+        }
+    }
+    document.rules.extend(object.rules);
+    Ok(document)
 }
 
 pub fn load_schema(schema_bytes: Option<&[u8]>) -> Result<Vec<ColumnSchema>, anyhow::Error> {
@@ -373,17 +933,32 @@ pub fn load_schema(schema_bytes: Option<&[u8]>) -> Result<Vec<ColumnSchema>, any
 pub fn load_schema_with_warnings(
     schema_bytes: Option<&[u8]>,
 ) -> Result<(Vec<ColumnSchema>, Vec<String>), anyhow::Error> {
-    match schema_bytes {
-        None => Ok((get_default_schema(), Vec::new())),
-        Some(bytes) => {
-            let raw: Vec<RawColumnSchema> = serde_json::from_slice(bytes)
-                .map_err(|e| anyhow::anyhow!("Invalid JSON in schema: {}", e))?;
-            let mut warnings = Vec::new();
-            let schema = resolve_raw_schema(raw, &mut warnings)?;
-            validate_schema(&schema)?;
-            Ok((schema, warnings))
-        }
-    }
+    let (document, warnings) = load_schema_document(schema_bytes)?;
+    Ok((document.columns, warnings))
+}
+
+/// Like `load_schema_with_warnings`, but keeps the schema's rules.
+pub fn load_schema_document(
+    schema_bytes: Option<&[u8]>,
+) -> Result<(SchemaDocument, Vec<String>), anyhow::Error> {
+    let value = match schema_bytes {
+        None => serde_json::Value::String(DEFAULT_SCHEMA_NAME.to_string()),
+        // A bare built-in name is accepted as well as a JSON document.
+        Some(bytes) => match std::str::from_utf8(bytes)
+            .ok()
+            .map(str::trim)
+            .filter(|name| builtin_schema_names().contains(name))
+        {
+            Some(name) => serde_json::Value::String(name.to_string()),
+            None => serde_json::from_slice(bytes)
+                .map_err(|e| anyhow::anyhow!("Invalid JSON in schema: {}", e))?,
+        },
+    };
+    let mut warnings = Vec::new();
+    let document = resolve_schema_value(value, None, &mut warnings, 0)?;
+    validate_schema(&document.columns)?;
+    validate_rules(&document.rules, &document.columns)?;
+    Ok((document, warnings))
 }
 
 pub fn validate_schema(schema: &[ColumnSchema]) -> Result<(), anyhow::Error> {
@@ -400,21 +975,68 @@ pub fn validate_schema(schema: &[ColumnSchema]) -> Result<(), anyhow::Error> {
         seen_names.insert(name);
 
         match col {
-            ColumnSchema::Int { bits, int_max, name, .. } => {
-                if *bits > 0 {
-                    let max_representable = (1u64 << bits) - 1;
-                    if *int_max > max_representable {
+            ColumnSchema::Int { bits, int_max, int_min, name, .. } => {
+                if int_max < int_min {
+                    return Err(anyhow::anyhow!(
+                        "Column {}: int_max {} is below int_min {}",
+                        name,
+                        int_max,
+                        int_min
+                    ));
+                }
+                if *bits > MAX_INT_BITS {
+                    return Err(anyhow::anyhow!("Column {}: bits={} exceeds {}", name, bits, MAX_INT_BITS));
+                }
+                let max_representable = int_max_for_bits(*bits);
+                if int_max - int_min > max_representable {
+                    return Err(anyhow::anyhow!(
+                        "Column {}: range {}..={} exceeds {}-bit capacity (at most {} above int_min)",
+                        name,
+                        int_min,
+                        int_max,
+                        bits,
+                        max_representable
+                    ));
+                }
+            }
+            ColumnSchema::Enum { bits, values, aliases, case_insensitive, name, .. } => {
+                if values.is_empty() {
+                    return Err(anyhow::anyhow!("Column {}: enum needs at least one value", name));
+                }
+                // Every spelling must map to one value, or the later one would silently
+                // win when cells are looked up.
+                let key = |s: &str| if *case_insensitive { s.to_ascii_lowercase() } else { s.to_string() };
+                let mut spellings: HashSet<String> = HashSet::new();
+                for value in values {
+                    // Repeated empty values are padding (the built-in default schema
+                    // has them) and decode the same, so only they may repeat.
+                    if !spellings.insert(key(value)) && !value.is_empty() {
                         return Err(anyhow::anyhow!(
-                            "Column {}: int_max {} exceeds {}-bit capacity ({})",
+                            "Column {}: enum value '{}' is listed twice{}",
                             name,
-                            int_max,
-                            bits,
-                            max_representable
+                            value,
+                            if *case_insensitive { " (ignoring case)" } else { "" }
+                        ));
+                    }
+                }
+                for (alias, target) in aliases {
+                    if !values.contains(target) {
+                        return Err(anyhow::anyhow!(
+                            "Column {}: alias '{}' points to '{}', which is not one of the enum values",
+                            name,
+                            alias,
+                            target
+                        ));
+                    }
+                    if !spellings.insert(key(alias)) {
+                        return Err(anyhow::anyhow!(
+                            "Column {}: alias '{}' collides with an enum value or another alias{}",
+                            name,
+                            alias,
+                            if *case_insensitive { " (ignoring case)" } else { "" }
                         ));
                     }
                 }
-            }
-            ColumnSchema::Enum { bits, values, name, .. } => {
                 let count = values.len();
                 if count > 1 {
                     let min_bits = (count as f64).log2().ceil() as u32;
@@ -429,9 +1051,394 @@ pub fn validate_schema(schema: &[ColumnSchema]) -> Result<(), anyhow::Error> {
                     }
                 }
             }
+            ColumnSchema::Sint { bits, int_max, name, .. } => {
+                if *bits > MAX_INT_BITS {
+                    return Err(anyhow::anyhow!("Column {}: bits={} exceeds {}", name, bits, MAX_INT_BITS));
+                }
+                if *bits == 1 {
+                    return Err(anyhow::anyhow!("Column {}: a 1-bit sint can only hold 0", name));
+                }
+                if *int_max > i128::MAX as u128 {
+                    return Err(anyhow::anyhow!(
+                        "Column {}: int_max {} exceeds the signed 128-bit range",
+                        name,
+                        int_max
+                    ));
+                }
+                if sint_bits_needed(*int_max) > *bits {
+                    return Err(anyhow::anyhow!(
+                        "Column {}: sint range -{}..={} exceeds {}-bit zigzag capacity (-{}..={})",
+                        name,
+                        int_max,
+                        int_max,
+                        bits,
+                        sint_max_for_bits(*bits),
+                        sint_max_for_bits(*bits)
+                    ));
+                }
+            }
+            ColumnSchema::Decimal { bits, min, max, step, precision, name, .. } => {
+                if !(min.is_finite() && max.is_finite() && step.is_finite()) || *step <= 0.0 || max < min {
+                    return Err(anyhow::anyhow!(
+                        "Column {}: decimal needs finite min <= max and step > 0",
+                        name
+                    ));
+                }
+                if !is_whole_steps(*min, *max, *step) {
+                    return Err(anyhow::anyhow!(
+                        "Column {}: decimal max {} is not a whole number of steps from min",
+                        name,
+                        max
+                    ));
+                }
+                if *precision > MAX_DECIMAL_PRECISION {
+                    return Err(anyhow::anyhow!(
+                        "Column {}: precision {} exceeds {}",
+                        name,
+                        precision,
+                        MAX_DECIMAL_PRECISION
+                    ));
+                }
+                let needed = int_bits_needed(decimal_max_index(*min, *max, *step).into());
+                if needed > *bits {
+                    return Err(anyhow::anyhow!(
+                        "Column {}: bits={} insufficient for decimal range (need at least {})",
+                        name,
+                        bits,
+                        needed
+                    ));
+                }
+            }
+            ColumnSchema::Bool { true_values, false_values, true_output, false_output, name, .. } => {
+                if true_values.is_empty() || false_values.is_empty() {
+                    return Err(anyhow::anyhow!(
+                        "Column {}: bool needs at least one true and one false spelling",
+                        name
+                    ));
+                }
+                let is_true = |s: &str| {
+                    s.eq_ignore_ascii_case(true_output) || true_values.iter().any(|t| t.eq_ignore_ascii_case(s))
+                };
+                let is_false = |s: &str| {
+                    s.eq_ignore_ascii_case(false_output) || false_values.iter().any(|f| f.eq_ignore_ascii_case(s))
+                };
+                if let Some(both) = true_values
+                    .iter()
+                    .chain(false_values)
+                    .chain([true_output, false_output])
+                    .find(|s| is_true(s) && is_false(s))
+                {
+                    return Err(anyhow::anyhow!(
+                        "Column {}: '{}' is accepted as both true and false",
+                        name,
+                        both
+                    ));
+                }
+            }
+            ColumnSchema::Text { max_length, name, .. } => {
+                if *max_length == 0 {
+                    return Err(anyhow::anyhow!("Column {}: text max_length must be at least 1", name));
+                }
+            }
+            ColumnSchema::Flags { bits, options, delimiter, name, .. } => {
+                if options.is_empty() || options.len() > 64 {
+                    return Err(anyhow::anyhow!(
+                        "Column {}: flags need between 1 and 64 options, got {}",
+                        name,
+                        options.len()
+                    ));
+                }
+                if *bits as usize != options.len() {
+                    return Err(anyhow::anyhow!(
+                        "Column {}: bits={} does not match {} flag options",
+                        name,
+                        bits,
+                        options.len()
+                    ));
+                }
+                if delimiter.is_empty() {
+                    return Err(anyhow::anyhow!("Column {}: flags delimiter cannot be empty", name));
+                }
+                let mut seen_options = HashSet::new();
+                for option in options {
+                    if option.trim().is_empty() || option.trim() != option || option.contains(delimiter.as_str()) {
+                        return Err(anyhow::anyhow!(
+                            "Column {}: flag option '{}' must be non-empty, have no surrounding whitespace and not contain the delimiter '{}'",
+                            name,
+                            option,
+                            delimiter
+                        ));
+                    }
+                    if !seen_options.insert(option) {
+                        return Err(anyhow::anyhow!("Column {}: duplicate flag option '{}'", name, option));
+                    }
+                }
+            }
+            ColumnSchema::List { bits, max_length, delimiter, element, name } => {
+                if *max_length == 0 {
+                    return Err(anyhow::anyhow!("Column {}: list max_length must be at least 1", name));
+                }
+                if *bits != list_length_bits(*max_length) {
+                    return Err(anyhow::anyhow!(
+                        "Column {}: bits={} does not match list max_length {}",
+                        name,
+                        bits,
+                        max_length
+                    ));
+                }
+                if delimiter.is_empty() {
+                    return Err(anyhow::anyhow!("Column {}: list delimiter cannot be empty", name));
+                }
+                if !matches!(element.kind(), ColumnKind::Int | ColumnKind::Enum | ColumnKind::Decimal)
+                    || element.nullable()
+                {
+                    return Err(anyhow::anyhow!(
+                        "Column {}: list elements must be non-nullable int, enum or decimal columns",
+                        name
+                    ));
+                }
+                if let Some(bad) = element
+                    .values()
+                    .into_iter()
+                    .flatten()
+                    .find(|v| v.trim().is_empty() || v.trim() != v.as_str() || v.contains(delimiter.as_str()))
+                {
+                    return Err(anyhow::anyhow!(
+                        "Column {}: list enum value '{}' must be non-empty, have no surrounding whitespace and not contain the delimiter '{}'",
+                        name,
+                        bad,
+                        delimiter
+                    ));
+                }
+                validate_schema(std::slice::from_ref(element))?;
+            }
+            ColumnSchema::Computed { expression, precision, name } => {
+                let parsed = Expression::parse(expression)
+                    .map_err(|e| anyhow::anyhow!("Column {}: invalid expression '{}': {}", name, expression, e))?;
+                // Only earlier columns may be referenced, which also rules out cycles.
+                for input in parsed.columns() {
+                    let kind = schema
+                        .iter()
+                        .take_while(|col| col.name() != name)
+                        .find(|col| col.name() == input)
+                        .map(ColumnSchema::kind)
+                        .ok_or_else(|| {
+                            anyhow::anyhow!("Column {}: expression uses {}, which is not an earlier column", name, input)
+                        })?;
+                    if matches!(kind, ColumnKind::Enum | ColumnKind::Text | ColumnKind::Flags | ColumnKind::List) {
+                        return Err(anyhow::anyhow!(
+                            "Column {}: expression uses {}, which is not numeric",
+                            name,
+                            input
+                        ));
+                    }
+                }
+                if let Some(precision) = precision.filter(|p| *p > MAX_DECIMAL_PRECISION) {
+                    return Err(anyhow::anyhow!(
+                        "Column {}: precision {} exceeds {}",
+                        name,
+                        precision,
+                        MAX_DECIMAL_PRECISION
+                    ));
+                }
+            }
         }
     }
 
     Ok(())
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialized_wide_int_bounds_reload() {
+        let source = r#"[
+            { "name": "Hash", "kind": "int", "int_max": "340282366920938463463374607431768211455" },
+            { "name": "Big", "kind": "int", "int_min": "18446744073709551616", "int_max": "18446744073709551716" },
+            { "name": "Small", "kind": "int", "int_max": 100 },
+            { "name": "Wide", "kind": "sint", "int_max": "170141183460469231731687303715884105727" }
+        ]"#;
+        let (schema, _) = load_schema_with_warnings(Some(source.as_bytes())).unwrap();
+        let json = serde_json::to_string(&schema).unwrap();
+        assert!(json.contains(r#""int_max":100"#));
+        assert!(json.contains(r#""int_min":"18446744073709551616""#));
+
+        let (reloaded, _) = load_schema_with_warnings(Some(json.as_bytes())).unwrap();
+        assert_eq!(serde_json::to_string(&reloaded).unwrap(), json);
+    }
+
+    #[test]
+    fn enum_spellings_must_be_unique() {
+        let load = |column: &str| load_schema(Some(format!("[{}]", column).as_bytes()));
+        let rejected = [
+            r#"{ "name": "c", "kind": "enum", "values": [] }"#,
+            r#"{ "name": "c", "kind": "enum", "values": ["Deep", "Deep"] }"#,
+            r#"{ "name": "c", "kind": "enum", "values": ["Deep", "DEEP"], "case_insensitive": true }"#,
+            r#"{ "name": "c", "kind": "enum", "values": ["None", "Deep"], "aliases": { "Deep": "None" } }"#,
+            r#"{ "name": "c", "kind": "enum", "values": ["None", "Deep"], "aliases": { "deep": "Deep" },
+                 "case_insensitive": true }"#,
+            r#"{ "name": "c", "kind": "enum", "values": ["None", "Deep"], "aliases": { "No": "None", "no": "Deep" },
+                 "case_insensitive": true }"#,
+        ];
+        for column in rejected {
+            assert!(load(column).is_err(), "{}", column);
+        }
+        let accepted = [
+            r#"{ "name": "c", "kind": "enum", "values": ["Deep", "DEEP"] }"#,
+            r#"{ "name": "c", "kind": "enum", "values": ["Deep", "", ""] }"#,
+            r#"{ "name": "c", "kind": "enum", "values": ["None", "Deep"], "aliases": { "No": "None", "no": "Deep" } }"#,
+        ];
+        for column in accepted {
+            assert!(load(column).is_ok(), "{}", column);
+        }
+    }
+
+    #[test]
+    fn one_bit_sint_is_rejected() {
+        let err = load_schema(Some(br#"[{ "name": "d", "kind": "sint", "bits": 1 }]"#)).unwrap_err();
+        assert!(err.to_string().contains("bits=1"), "{}", err);
+        let (schema, _) = load_schema_with_warnings(Some(br#"[{ "name": "d", "kind": "sint", "bits": 2 }]"#)).unwrap();
+        assert_eq!(schema[0].int_max(), Some(1));
+    }
+
+    #[test]
+    fn non_integer_bounds_name_the_field() {
+        let err = load_schema(Some(br#"[{ "name": "n", "kind": "int", "int_max": 1e30 }]"#)).unwrap_err();
+        assert!(err.to_string().contains("int_max/int_min must be an integer or decimal string"), "{}", err);
+    }
+
+    fn names(schema: &[ColumnSchema]) -> Vec<&str> {
+        schema.iter().map(ColumnSchema::name).collect()
+    }
+
+    #[test]
+    fn groups_expand_in_var_order() {
+        let (schema, _) = load_schema_with_warnings(Some(br#"[
+            { "name": "Team", "kind": "int", "int_max": 9999 },
+            { "kind": "group",
+              "vars": [{ "name": "phase", "values": ["Auton", "Teleop"] },
+                       { "name": "level", "values": ["L1", "L2", "L3"] }],
+              "columns": [{ "name": "{phase}{level}", "kind": "int", "int_max": 12 },
+                          { "name": "{phase}{level}Missed", "kind": "enum", "values": ["{level}", "none"] }] },
+            { "name": "Notes", "kind": "int", "int_max": 1 }
+        ]"#)).unwrap();
+
+        // 2 x 3 bindings of 2 columns each, between the surrounding columns.
+        assert_eq!(schema.len(), 1 + 2 * 3 * 2 + 1);
+        assert_eq!(
+            names(&schema)[..7],
+            ["Team", "AutonL1", "AutonL1Missed", "AutonL2", "AutonL2Missed", "AutonL3", "AutonL3Missed"]
+        );
+        assert_eq!(names(&schema)[12..], ["TeleopL3Missed", "Notes"]);
+        // Every string of the template is substituted, not just the name.
+        assert_eq!(schema[4].values().unwrap(), &["L2", "none"]);
+    }
+
+    #[test]
+    fn group_names_must_not_collide() {
+        let load = |columns: &str| load_schema(Some(format!("[{}]", columns).as_bytes()));
+        let group = r#"{ "kind": "group", "vars": [{ "name": "n", "values": ["1", "2"] }],
+                         "columns": [{ "name": "Cycle{n}", "kind": "int", "int_max": 9 }] }"#;
+        assert!(load(group).is_ok());
+
+        let existing = format!(r#"{{ "name": "Cycle2", "kind": "int", "int_max": 9 }}, {}"#, group);
+        let err = load(&existing).unwrap_err();
+        assert!(err.to_string().contains("Cycle2"), "{}", err);
+
+        let unused_var = r#"{ "kind": "group", "vars": [{ "name": "n", "values": ["1", "2"] }],
+                              "columns": [{ "name": "Cycle", "kind": "int", "int_max": 9 }] }"#;
+        assert!(load(unused_var).is_err());
+    }
+
+    #[test]
+    fn nested_or_empty_groups_are_rejected() {
+        let rejected = [
+            (r#"{ "kind": "group", "vars": [{ "name": "a", "values": ["1"] }],
+                  "columns": [{ "kind": "group", "vars": [{ "name": "b", "values": ["2"] }],
+                                "columns": [{ "name": "c{a}{b}", "kind": "int", "int_max": 9 }] }] }"#,
+             "nested"),
+            (r#"{ "kind": "group", "vars": [], "columns": [{ "name": "c", "kind": "int", "int_max": 9 }] }"#,
+             "at least one var"),
+            (r#"{ "kind": "group", "vars": [{ "name": "a", "values": ["1"] }], "columns": [] }"#,
+             "at least one var and one column"),
+            (r#"{ "kind": "group", "vars": [{ "name": "a", "values": [] }],
+                  "columns": [{ "name": "c{a}", "kind": "int", "int_max": 9 }] }"#,
+             "var a has no values"),
+            (r#"{ "kind": "group", "vars": [{ "name": "", "values": ["1"] }],
+                  "columns": [{ "name": "c", "kind": "int", "int_max": 9 }] }"#,
+             "names cannot be empty"),
+        ];
+        for (group, message) in rejected {
+            let err = load_schema(Some(format!("[{}]", group).as_bytes())).unwrap_err();
+            assert!(err.to_string().contains(message), "{}: {}", group, err);
+        }
+    }
+
+    const BASE: &str = r#"[
+        { "name": "Team", "kind": "int", "int_max": 9999 },
+        { "name": "Score", "kind": "int", "int_max": 99 },
+        { "name": "Climb", "kind": "enum", "values": ["None", "Deep"] }
+    ]"#;
+
+    fn extend(edits: &str) -> Result<Vec<ColumnSchema>, anyhow::Error> {
+        load_schema(Some(format!(r#"{{ "extends": {}, {} }}"#, BASE, edits).as_bytes()))
+    }
+
+    #[test]
+    fn extends_overrides_in_place_removes_and_appends() {
+        let schema = extend(r#""remove": ["Team"], "columns": [
+            { "name": "Notes", "kind": "int", "int_max": 1 },
+            { "name": "Score", "kind": "int", "int_max": 250 }
+        ]"#).unwrap();
+        assert_eq!(names(&schema), ["Score", "Climb", "Notes"]);
+        assert_eq!(schema[0].int_max(), Some(250));
+
+        let err = extend(r#""remove": ["Auton"]"#).unwrap_err();
+        assert!(err.to_string().contains("Cannot remove column Auton"), "{}", err);
+        // The merged schema is validated like any other.
+        assert!(extend(r#""remove": ["Team", "Score", "Climb"]"#).is_err());
+    }
+
+    #[test]
+    fn extends_chains_through_files() {
+        let dir = std::env::temp_dir().join(format!("schema-extends-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("events")).unwrap();
+        std::fs::write(dir.join("season.json"), BASE).unwrap();
+        // Relative paths resolve against the extending file's directory.
+        std::fs::write(
+            dir.join("events/district.json"),
+            r#"{ "extends": "../season.json", "remove": ["Climb"] }"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("events/final.json"),
+            r#"{ "extends": "district.json", "columns": [{ "name": "Team", "kind": "int", "int_max": 99999 }] }"#,
+        )
+        .unwrap();
+
+        let schema = load_schema_from_json(&dir.join("events/final.json"));
+        let missing = load_schema(Some(br#"{ "extends": "no-such-season.json" }"#));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let schema = schema.unwrap();
+        assert_eq!(names(&schema), ["Team", "Score"]);
+        assert_eq!(schema[0].int_max(), Some(99999));
+        let err = missing.unwrap_err();
+        assert!(err.to_string().contains("Schema file not found: no-such-season.json"), "{}", err);
+    }
+
+    #[test]
+    fn inline_bases_chain_and_unknown_fields_are_rejected() {
+        let chained = format!(
+            r#"{{ "extends": {{ "extends": {}, "remove": ["Climb"] }}, "remove": ["Score"] }}"#,
+            BASE
+        );
+        assert_eq!(names(&load_schema(Some(chained.as_bytes())).unwrap()), ["Team"]);
+
+        assert!(extend(r#""add": []"#).is_err());
+        assert!(load_schema(Some(br#"{ "extends": 7 }"#)).is_err());
+    }
+}
@@ -0,0 +1,374 @@
+use crate::common::schema::ColumnSchema;
+
+// Compact binary form of a resolved schema, embedded in self-describing payloads:
+// varint column count, then per column a kind byte, the name, the bit width and the
+// kind-specific fields. Strings are a varint byte length followed by UTF-8. The
+// kind byte's top bit marks a nullable column. A list column's fields end with its
+// element, written as a column of its own.
+
+const KIND_INT: u8 = 0;
+const KIND_ENUM: u8 = 1;
+const KIND_SINT: u8 = 2;
+const KIND_DECIMAL: u8 = 3;
+const KIND_BOOL: u8 = 4;
+const KIND_TEXT: u8 = 5;
+const KIND_FLAGS: u8 = 6;
+const KIND_COMPUTED: u8 = 7;
+const KIND_LIST: u8 = 8;
+
+/// Stands in for "no precision" on computed columns.
+const NO_PRECISION: u8 = 0xFF;
+
+const NULLABLE_BIT: u8 = 0x80;
+
+pub fn schema_to_bytes(schema: &[ColumnSchema]) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_varint(&mut out, schema.len() as u64);
+    for col in schema {
+        write_column(&mut out, col);
+    }
+    out
+}
+
+fn write_column(out: &mut Vec<u8>, col: &ColumnSchema) {
+    let nullable_bit = if col.nullable() { NULLABLE_BIT } else { 0 };
+    match col {
+        ColumnSchema::Int { name, bits, int_max, int_min, .. } => {
+            out.push(KIND_INT | nullable_bit);
+            write_string(out, name);
+            out.push(*bits as u8);
+            write_wide_varint(out, *int_max);
+            write_wide_varint(out, *int_min);
+        }
+        ColumnSchema::Enum { name, bits, values, aliases, case_insensitive, .. } => {
+            out.push(KIND_ENUM | nullable_bit);
+            write_string(out, name);
+            out.push(*bits as u8);
+            write_strings(out, values);
+            write_varint(out, aliases.len() as u64);
+            for (alias, target) in aliases {
+                write_string(out, alias);
+                write_string(out, target);
+            }
+            out.push(*case_insensitive as u8);
+        }
+        ColumnSchema::Sint { name, bits, int_max, .. } => {
+            out.push(KIND_SINT | nullable_bit);
+            write_string(out, name);
+            out.push(*bits as u8);
+            write_wide_varint(out, *int_max);
+        }
+        ColumnSchema::Decimal { name, bits, min, max, step, precision, .. } => {
+            out.push(KIND_DECIMAL | nullable_bit);
+            write_string(out, name);
+            out.push(*bits as u8);
+            for value in [min, max, step] {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+            out.push(*precision as u8);
+        }
+        ColumnSchema::Bool { name, true_values, false_values, true_output, false_output, .. } => {
+            out.push(KIND_BOOL | nullable_bit);
+            write_string(out, name);
+            out.push(1);
+            write_strings(out, true_values);
+            write_strings(out, false_values);
+            write_string(out, true_output);
+            write_string(out, false_output);
+        }
+        ColumnSchema::Text { name, max_length, .. } => {
+            out.push(KIND_TEXT | nullable_bit);
+            write_string(out, name);
+            out.push(0);
+            write_varint(out, *max_length as u64);
+        }
+        ColumnSchema::Flags { name, bits, options, delimiter, .. } => {
+            out.push(KIND_FLAGS | nullable_bit);
+            write_string(out, name);
+            out.push(*bits as u8);
+            write_strings(out, options);
+            write_string(out, delimiter);
+        }
+        ColumnSchema::Computed { name, expression, precision } => {
+            out.push(KIND_COMPUTED);
+            write_string(out, name);
+            out.push(0);
+            write_string(out, expression);
+            out.push(precision.map_or(NO_PRECISION, |p| p as u8));
+        }
+        ColumnSchema::List { name, bits, max_length, delimiter, element } => {
+            out.push(KIND_LIST);
+            write_string(out, name);
+            out.push(*bits as u8);
+            write_varint(out, *max_length as u64);
+            write_string(out, delimiter);
+            write_column(out, element);
+        }
+    }
+}
+
+/// Parses a schema written by `schema_to_bytes`, returning it with the number of bytes read.
+pub fn schema_from_bytes(bytes: &[u8]) -> Result<(Vec<ColumnSchema>, usize), anyhow::Error> {
+    let mut reader = ByteReader::new(bytes, "Embedded schema");
+
+    let count = reader.varint()? as usize;
+    let mut schema = Vec::with_capacity(count.min(bytes.len()));
+    for _ in 0..count {
+        schema.push(read_column(&mut reader, false)?);
+    }
+
+    Ok((schema, reader.pos))
+}
+
+/// Reads one column; `in_list` is set for a list's element, which can't be a list itself.
+fn read_column(reader: &mut ByteReader, in_list: bool) -> Result<ColumnSchema, anyhow::Error> {
+    let kind_byte = reader.byte()?;
+    let nullable = kind_byte & NULLABLE_BIT != 0;
+    let kind = kind_byte & !NULLABLE_BIT;
+    let name = reader.string()?;
+    if in_list && kind == KIND_LIST {
+        return Err(anyhow::anyhow!("Embedded schema nests lists in column {}", name));
+    }
+    let bits = reader.byte()? as u32;
+    let col = match kind {
+        KIND_INT => ColumnSchema::Int {
+            name,
+            bits,
+            int_max: reader.wide_varint()?,
+            int_min: reader.wide_varint()?,
+            nullable,
+        },
+        KIND_ENUM => ColumnSchema::Enum {
+            name,
+            bits,
+            values: reader.strings()?,
+            aliases: {
+                let count = reader.varint()? as usize;
+                (0..count)
+                    .map(|_| Ok((reader.string()?, reader.string()?)))
+                    .collect::<Result<_, anyhow::Error>>()?
+            },
+            case_insensitive: reader.byte()? != 0,
+            nullable,
+        },
+        KIND_SINT => ColumnSchema::Sint {
+            name,
+            bits,
+            int_max: reader.wide_varint()?,
+            nullable,
+        },
+        KIND_DECIMAL => ColumnSchema::Decimal {
+            name,
+            bits,
+            min: reader.f64()?,
+            max: reader.f64()?,
+            step: reader.f64()?,
+            precision: reader.byte()? as u32,
+            nullable,
+        },
+        KIND_BOOL => ColumnSchema::Bool {
+            name,
+            true_values: reader.strings()?,
+            false_values: reader.strings()?,
+            true_output: reader.string()?,
+            false_output: reader.string()?,
+            nullable,
+        },
+        KIND_TEXT => ColumnSchema::Text {
+            name,
+            max_length: reader.varint()? as u32,
+            nullable,
+        },
+        KIND_FLAGS => ColumnSchema::Flags {
+            name,
+            bits,
+            options: reader.strings()?,
+            delimiter: reader.string()?,
+            nullable,
+        },
+        KIND_COMPUTED => ColumnSchema::Computed {
+            name,
+            expression: reader.string()?,
+            precision: Some(reader.byte()?)
+                .filter(|&p| p != NO_PRECISION)
+                .map(u32::from),
+        },
+        KIND_LIST => ColumnSchema::List {
+            name,
+            bits,
+            max_length: reader.varint()? as u32,
+            delimiter: reader.string()?,
+            element: Box::new(read_column(reader, true)?),
+        },
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Embedded schema has unknown column kind {} for column {}",
+                kind,
+                name
+            ))
+        }
+    };
+    Ok(col)
+}
+
+pub(crate) fn write_varint(out: &mut Vec<u8>, value: u64) {
+    write_wide_varint(out, value.into());
+}
+
+/// Same encoding as `write_varint`, for int ranges wider than 64 bits.
+fn write_wide_varint(out: &mut Vec<u8>, mut value: u128) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+pub(crate) fn write_string(out: &mut Vec<u8>, s: &str) {
+    write_varint(out, s.len() as u64);
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn write_strings(out: &mut Vec<u8>, strings: &[String]) {
+    write_varint(out, strings.len() as u64);
+    for s in strings {
+        write_string(out, s);
+    }
+}
+
+/// Cursor over length-prefixed binary data; `what` names the data in error messages.
+pub(crate) struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pub(crate) pos: usize,
+    what: &'static str,
+}
+
+impl<'a> ByteReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8], what: &'static str) -> Self {
+        ByteReader { bytes, pos: 0, what }
+    }
+
+    pub(crate) fn byte(&mut self) -> Result<u8, anyhow::Error> {
+        let b = *self
+            .bytes
+            .get(self.pos)
+            .ok_or_else(|| anyhow::anyhow!("{} is truncated", self.what))?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    pub(crate) fn f64(&mut self) -> Result<f64, anyhow::Error> {
+        let mut buf = [0u8; 8];
+        for b in buf.iter_mut() {
+            *b = self.byte()?;
+        }
+        Ok(f64::from_le_bytes(buf))
+    }
+
+    pub(crate) fn varint(&mut self) -> Result<u64, anyhow::Error> {
+        u64::try_from(self.wide_varint()?)
+            .map_err(|_| anyhow::anyhow!("{} has an overlong varint", self.what))
+    }
+
+    pub(crate) fn wide_varint(&mut self) -> Result<u128, anyhow::Error> {
+        let mut value = 0u128;
+        for shift in (0..128).step_by(7) {
+            let b = self.byte()?;
+            // The last byte (shift 126) only has room for the top two bits.
+            if shift == 126 && b & 0x7c != 0 {
+                return Err(anyhow::anyhow!("{} has a varint past 128 bits", self.what));
+            }
+            value |= ((b & 0x7f) as u128) << shift;
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(anyhow::anyhow!("{} has an overlong varint", self.what))
+    }
+
+    pub(crate) fn string(&mut self) -> Result<String, anyhow::Error> {
+        let len = self.varint()? as usize;
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| anyhow::anyhow!("{} is truncated", self.what))?;
+        let s = std::str::from_utf8(&self.bytes[self.pos..end])
+            .map_err(|e| anyhow::anyhow!("{} has invalid UTF-8: {}", self.what, e))?
+            .to_string();
+        self.pos = end;
+        Ok(s)
+    }
+
+    pub(crate) fn strings(&mut self) -> Result<Vec<String>, anyhow::Error> {
+        let count = self.varint()? as usize;
+        (0..count).map(|_| self.string()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::schema::load_schema;
+
+    #[test]
+    fn every_column_kind_round_trips() {
+        let schema = load_schema(Some(
+            br#"[
+                { "name": "Team", "kind": "int", "int_max": 9999 },
+                { "name": "Offset", "kind": "int", "int_min": 1000, "int_max": 1100, "nullable": true },
+                { "name": "Serial", "kind": "int", "bits": 128 },
+                { "name": "Delta", "kind": "sint", "int_max": 50 },
+                { "name": "Wide", "kind": "sint", "bits": 128, "nullable": true },
+                { "name": "Climb", "kind": "enum", "values": ["None", "Park", "Deep"],
+                  "aliases": { "no": "None" }, "case_insensitive": true, "nullable": true },
+                { "name": "Cycle", "kind": "decimal", "min": -1.5, "max": 30, "step": 0.25 },
+                { "name": "Died", "kind": "bool", "true_values": ["x"], "false_values": [""],
+                  "true_output": "X", "false_output": "-", "nullable": true },
+                { "name": "Notes", "kind": "text", "max_length": 40, "nullable": true },
+                { "name": "Pickup", "kind": "flags", "options": ["Floor", "Station"], "delimiter": "|" },
+                { "name": "Ratio", "kind": "computed", "expression": "Team / Delta", "precision": 2 },
+                { "name": "Sum", "kind": "computed", "expression": "Team + Delta" },
+                { "name": "Cycles", "kind": "list", "element": { "kind": "decimal", "min": 0, "max": 30, "step": 0.1 },
+                  "max_length": 20 },
+                { "name": "Spots", "kind": "list", "element": { "kind": "enum", "values": ["A", "B"] },
+                  "max_length": 3, "delimiter": "/" }
+            ]"#,
+        ))
+        .unwrap();
+        let bytes = schema_to_bytes(&schema);
+        let (decoded, len) = schema_from_bytes(&bytes).unwrap();
+        assert_eq!(len, bytes.len());
+        assert_eq!(
+            serde_json::to_string(&decoded).unwrap(),
+            serde_json::to_string(&schema).unwrap()
+        );
+    }
+
+    #[test]
+    fn truncated_and_unknown_kinds_are_rejected() {
+        let schema = load_schema(Some(br#"[{ "name": "Team", "kind": "int", "int_max": 9999 }]"#)).unwrap();
+        let bytes = schema_to_bytes(&schema);
+        assert!(schema_from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        let mut unknown = bytes.clone();
+        unknown[1] = 0x7f;
+        assert!(schema_from_bytes(&unknown).is_err());
+    }
+
+    #[test]
+    fn wide_varint_round_trips_u128_max() {
+        let mut out = Vec::new();
+        write_wide_varint(&mut out, u128::MAX);
+        assert_eq!(out.len(), 19);
+        assert_eq!(ByteReader::new(&out, "test").wide_varint().unwrap(), u128::MAX);
+    }
+
+    #[test]
+    fn wide_varint_rejects_bits_past_128() {
+        let mut out = Vec::new();
+        write_wide_varint(&mut out, u128::MAX);
+        *out.last_mut().unwrap() |= 0x04;
+        assert!(ByteReader::new(&out, "test").wide_varint().is_err());
+    }
+}
@@ -0,0 +1,362 @@
+use crate::common::schema::{schema_fingerprint, ColumnSchema};
+use std::collections::BTreeMap;
+
+/// Built-in schema used when neither the caller nor the payload names one.
+pub const DEFAULT_SCHEMA_NAME: &str = "frc-2025-reefscape";
+
+/// Named schemas compiled into every build. Entries must never change once released:
+/// payloads record the name they were encoded with and decode against it.
+const BUILTIN_SCHEMAS: [(&str, SchemaBuilder); 1] = [(DEFAULT_SCHEMA_NAME, frc_2025_reefscape)];
+
+type SchemaBuilder = fn() -> Vec<ColumnSchema>;
+
+pub fn builtin_schema_names() -> Vec<&'static str> {
+    BUILTIN_SCHEMAS.iter().map(|(name, _)| *name).collect()
+}
+
+pub fn builtin_schema(name: &str) -> Result<Vec<ColumnSchema>, anyhow::Error> {
+    BUILTIN_SCHEMAS
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(_, schema)| schema())
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "Unknown built-in schema '{}' (known: {})",
+                name,
+                builtin_schema_names().join(", ")
+            )
+        })
+}
+
+/// Name of the built-in schema identical to `schema`, if there is one.
+pub fn builtin_schema_name_for(schema: &[ColumnSchema]) -> Result<Option<&'static str>, anyhow::Error> {
+    let fingerprint = schema_fingerprint(schema)?;
+    for (name, builtin) in BUILTIN_SCHEMAS {
+        if schema_fingerprint(&builtin())? == fingerprint {
+            return Ok(Some(name));
+        }
+    }
+    Ok(None)
+}
+
+fn frc_2025_reefscape() -> Vec<ColumnSchema> {
+    vec![
+        ColumnSchema::Enum {
+            name: "ScoutName".to_string(),
+            bits: 2,
+            values: vec!["Jude".to_string(), "Dillon".to_string(), "".to_string(), "".to_string()],
+            aliases: BTreeMap::new(),
+            case_insensitive: false,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "MatchNumber".to_string(),
+            bits: 8,
+            int_max: 200,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "TeamNumber".to_string(),
+            bits: 14,
+            int_max: 16383,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "Mobility".to_string(),
+            bits: 1,
+            int_max: 1,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "AutonL1Attempted".to_string(),
+            bits: 4,
+            int_max: 12,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "AutonL1Scored".to_string(),
+            bits: 4,
+            int_max: 12,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "AutonL2Attempted".to_string(),
+            bits: 4,
+            int_max: 12,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "AutonL2Scored".to_string(),
+            bits: 4,
+            int_max: 12,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "AutonL3Attempted".to_string(),
+            bits: 4,
+            int_max: 12,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "AutonL3Scored".to_string(),
+            bits: 4,
+            int_max: 12,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "AutonL4Attempted".to_string(),
+            bits: 4,
+            int_max: 12,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "AutonL4Scored".to_string(),
+            bits: 4,
+            int_max: 12,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "AutonBargeAttempted".to_string(),
+            bits: 0,
+            int_max: 0,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "AutonBargeScored".to_string(),
+            bits: 0,
+            int_max: 0,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "AutonProcessorAttempted".to_string(),
+            bits: 0,
+            int_max: 0,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "AutonProcessorScored".to_string(),
+            bits: 0,
+            int_max: 0,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "AutonAlgaeRemoved".to_string(),
+            bits: 0,
+            int_max: 0,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "TeleopL1Attempted".to_string(),
+            bits: 4,
+            int_max: 12,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "TeleopL1Scored".to_string(),
+            bits: 4,
+            int_max: 12,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "TeleopL2Attempted".to_string(),
+            bits: 4,
+            int_max: 12,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "TeleopL2Scored".to_string(),
+            bits: 4,
+            int_max: 12,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "TeleopL3Attempted".to_string(),
+            bits: 4,
+            int_max: 12,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "TeleopL3Scored".to_string(),
+            bits: 4,
+            int_max: 12,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "TeleopL4Attempted".to_string(),
+            bits: 4,
+            int_max: 12,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "TeleopL4Scored".to_string(),
+            bits: 4,
+            int_max: 12,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "TeleopBargeAttempted".to_string(),
+            bits: 3,
+            int_max: 7,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "TeleopBargeScored".to_string(),
+            bits: 3,
+            int_max: 7,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "TeleopProcessorAttempted".to_string(),
+            bits: 3,
+            int_max: 7,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "TeleopProcessorScored".to_string(),
+            bits: 3,
+            int_max: 7,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "TeleopAlgaeRemoved".to_string(),
+            bits: 3,
+            int_max: 7,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Int {
+            name: "ClimbSuccessful".to_string(),
+            bits: 1,
+            int_max: 1,
+            int_min: 0,
+            nullable: false,
+        },
+        ColumnSchema::Enum {
+            name: "Climb".to_string(),
+            bits: 2,
+            values: vec!["None".to_string(), "Shallow".to_string(), "Deep".to_string(), "Park".to_string()],
+            aliases: BTreeMap::new(),
+            case_insensitive: false,
+            nullable: false,
+        },
+        ColumnSchema::Enum {
+            name: "Breakdown".to_string(),
+            bits: 1,
+            values: vec!["False".to_string(), "True".to_string()],
+            aliases: BTreeMap::new(),
+            case_insensitive: false,
+            nullable: false,
+        },
+        ColumnSchema::Enum {
+            name: "DefenseDescription".to_string(),
+            bits: 0,
+            values: vec!["".to_string()],
+            aliases: BTreeMap::new(),
+            case_insensitive: false,
+            nullable: false,
+        },
+        ColumnSchema::Enum {
+            name: "Notes".to_string(),
+            bits: 1,
+            values: vec!["".to_string(), "Some note".to_string()],
+            aliases: BTreeMap::new(),
+            case_insensitive: false,
+            nullable: false,
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::packed_header::{read_packed, write_packed};
+    use crate::common::schema::load_schema;
+    use crate::decoder::data_unpacker::decode;
+    use crate::encoder::data_packer::encode;
+
+    /// A valid row for `schema`: the last value of each enum, the largest int.
+    fn sample_row(schema: &[ColumnSchema]) -> Vec<String> {
+        schema
+            .iter()
+            .map(|col| match col {
+                ColumnSchema::Enum { values, .. } => values[values.len() - 1].clone(),
+                ColumnSchema::Int { int_max, .. } => int_max.to_string(),
+                _ => "0".to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn builtin_names_resolve_and_are_recognised() {
+        let builtin = builtin_schema(DEFAULT_SCHEMA_NAME).unwrap();
+        for source in [&b"frc-2025-reefscape"[..], b"\"frc-2025-reefscape\"", b" frc-2025-reefscape\n"] {
+            let loaded = load_schema(Some(source)).unwrap();
+            assert_eq!(schema_fingerprint(&loaded).unwrap(), schema_fingerprint(&builtin).unwrap());
+        }
+        assert_eq!(builtin_schema_name_for(&builtin).unwrap(), Some(DEFAULT_SCHEMA_NAME));
+
+        let mut edited = builtin;
+        edited.pop();
+        assert_eq!(builtin_schema_name_for(&edited).unwrap(), None);
+
+        let err = builtin_schema("frc-2099").unwrap_err();
+        assert_eq!(err.to_string(), "Unknown built-in schema 'frc-2099' (known: frc-2025-reefscape)");
+    }
+
+    #[test]
+    fn payloads_record_the_builtin_name_and_decode_without_a_schema() {
+        let schema = load_schema(Some(b"frc-2025-reefscape")).unwrap();
+        let headers: Vec<String> = schema.iter().map(|col| col.name().to_string()).collect();
+        let rows = vec![sample_row(&schema)];
+        let packed = encode(&headers, &rows, Some(&schema)).unwrap();
+
+        let (header, _) = read_packed(&packed).unwrap();
+        assert_eq!(header.schema_name.as_deref(), Some(DEFAULT_SCHEMA_NAME));
+        assert_eq!(decode(&packed, None).unwrap(), (headers, rows));
+
+        // A custom schema records no name.
+        let custom = load_schema(Some(br#"[{ "name": "Team", "kind": "int", "int_max": 9999 }]"#)).unwrap();
+        let packed = encode(&["Team".to_string()], &[vec!["254".to_string()]], Some(&custom)).unwrap();
+        assert_eq!(read_packed(&packed).unwrap().0.schema_name, None);
+    }
+
+    #[test]
+    fn unknown_recorded_names_are_reported() {
+        let body = zstd::encode_all(&[][..], 3).unwrap();
+        let packed = write_packed(0, 0, 0, Some("frc-2099-future"), &body).unwrap();
+        let err = decode(&packed, None).unwrap_err().to_string();
+        assert!(
+            err.starts_with("Payload was encoded with Unknown built-in schema 'frc-2099-future'"),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,88 @@
+use crate::common::schema_codec::{write_string, write_varint, ByteReader};
+use std::collections::HashMap;
+
+// Deduplicated strings shared by every text column of a payload, written between
+// the embedded schema and the bitplanes: a varint count, then each string as a
+// varint byte length followed by UTF-8. Text columns store indices into it.
+
+#[derive(Debug, Default)]
+pub struct StringTable {
+    strings: Vec<String>,
+    indices: HashMap<String, u64>,
+}
+
+impl StringTable {
+    /// Returns the index of `s`, adding it to the table if it is new.
+    pub fn intern(&mut self, s: &str) -> u64 {
+        if let Some(&index) = self.indices.get(s) {
+            return index;
+        }
+        let index = self.strings.len() as u64;
+        self.strings.push(s.to_string());
+        self.indices.insert(s.to_string(), index);
+        index
+    }
+
+    /// Bit width of the indices stored in text columns.
+    pub fn index_bits(&self) -> u32 {
+        index_bits(self.strings.len())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_varint(&mut out, self.strings.len() as u64);
+        for s in &self.strings {
+            write_string(&mut out, s);
+        }
+        out
+    }
+}
+
+/// Bits needed to index a table of `len` strings.
+pub fn index_bits(len: usize) -> u32 {
+    usize::BITS - len.saturating_sub(1).leading_zeros()
+}
+
+/// Parses a table written by `StringTable::to_bytes`, returning it with the number of bytes read.
+pub fn string_table_from_bytes(bytes: &[u8]) -> Result<(Vec<String>, usize), anyhow::Error> {
+    let mut reader = ByteReader::new(bytes, "String table");
+    let strings = reader.strings()?;
+    Ok((strings, reader.pos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_and_empty_strings_share_one_entry() {
+        let mut table = StringTable::default();
+        let indices: Vec<u64> = ["defended", "", "defended", "", "fast"].iter().map(|s| table.intern(s)).collect();
+        assert_eq!(indices, [0, 1, 0, 1, 2]);
+        assert_eq!(table.index_bits(), 2);
+
+        let mut bytes = table.to_bytes();
+        bytes.extend_from_slice(b"bitplanes");
+        let (strings, used) = string_table_from_bytes(&bytes).unwrap();
+        assert_eq!(strings, ["defended", "", "fast"]);
+        assert_eq!(&bytes[used..], b"bitplanes");
+    }
+
+    #[test]
+    fn index_width_grows_with_the_table() {
+        assert_eq!([0, 1, 2, 3, 4, 5, 256, 257].map(index_bits), [0, 0, 1, 2, 2, 3, 8, 9]);
+        let (strings, used) = string_table_from_bytes(&StringTable::default().to_bytes()).unwrap();
+        assert!(strings.is_empty());
+        assert_eq!(used, 1);
+    }
+
+    #[test]
+    fn truncated_tables_are_errors() {
+        let mut table = StringTable::default();
+        table.intern("defended");
+        let bytes = table.to_bytes();
+        for len in 0..bytes.len() {
+            assert!(string_table_from_bytes(&bytes[..len]).is_err(), "{} bytes", len);
+        }
+    }
+}
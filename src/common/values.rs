@@ -0,0 +1,167 @@
+use crate::common::schema::{decimal_max_index, ColumnSchema};
+use std::collections::HashMap;
+
+// Cell-to-value conversions shared by the packer, the unpacker and the rule checker,
+// so every side agrees on what a cell stores.
+
+/// A CSV cell as the encoder sees it: trimmed, with line breaks folded to spaces.
+pub(crate) fn normalize_cell(cell: &str) -> String {
+    let normalized = cell.trim().replace('\n', " ").replace('\r', "");
+    if normalized == " " {
+        String::new()
+    } else {
+        normalized
+    }
+}
+
+/// Maps signed values onto unsigned codes: 0, -1, 1, -2, 2, ... become 0, 1, 2, 3, 4, ...
+pub fn zigzag_encode(value: i128) -> u128 {
+    ((value << 1) ^ (value >> 127)) as u128
+}
+
+/// Inverse of `zigzag_encode`.
+pub fn zigzag_decode(code: u128) -> i128 {
+    ((code >> 1) as i128) ^ -((code & 1) as i128)
+}
+
+/// Key an enum spelling is looked up by.
+fn enum_key(s: &str, case_insensitive: bool) -> String {
+    if case_insensitive {
+        s.to_ascii_lowercase()
+    } else {
+        s.to_string()
+    }
+}
+
+/// Spelling-to-index map for an enum column, or a list of enums; `None` for other kinds.
+pub(crate) fn enum_lookup(col: &ColumnSchema) -> Option<HashMap<String, u128>> {
+    match col {
+        ColumnSchema::Enum { values, aliases, case_insensitive, .. } => {
+            let key = |s: &str| enum_key(s, *case_insensitive);
+            let mut lookup: HashMap<String, u128> = values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (key(v), i as u128))
+                .collect();
+            for (alias, target) in aliases {
+                let index = lookup[&key(target)];
+                lookup.insert(key(alias), index);
+            }
+            Some(lookup)
+        }
+        ColumnSchema::List { element, .. } => enum_lookup(element),
+        ColumnSchema::Int { .. }
+        | ColumnSchema::Sint { .. }
+        | ColumnSchema::Decimal { .. }
+        | ColumnSchema::Bool { .. }
+        | ColumnSchema::Text { .. }
+        | ColumnSchema::Flags { .. }
+        | ColumnSchema::Computed { .. } => None,
+    }
+}
+
+/// Packed value of one normalized, non-empty cell of a column whose values fit in its
+/// own bits (every kind but text, list and computed). `enum_lookup` comes from
+/// `enum_lookup(col)`.
+pub(crate) fn scalar_value(
+    col: &ColumnSchema,
+    raw: &str,
+    enum_lookup: Option<&HashMap<String, u128>>,
+) -> Result<u128, anyhow::Error> {
+    let value = match col {
+        ColumnSchema::Int { int_max, int_min, name, .. } => {
+            let val: u128 = raw.to_string().parse()
+                .map_err(|_| anyhow::anyhow!("Invalid integer value '{}' for column {}", raw, name))?;
+            if val > *int_max {
+                return Err(anyhow::anyhow!(
+                    "Value {} exceeds int_max {} for column {}",
+                    val,
+                    int_max,
+                    name
+                ));
+            }
+            if val < *int_min {
+                return Err(anyhow::anyhow!(
+                    "Value {} is below int_min {} for column {}",
+                    val,
+                    int_min,
+                    name
+                ));
+            }
+            val - int_min
+        }
+        ColumnSchema::Sint { int_max, name, .. } => {
+            let val: i128 = raw.parse()
+                .map_err(|_| anyhow::anyhow!("Invalid integer value '{}' for column {}", raw, name))?;
+            if val.unsigned_abs() > *int_max {
+                return Err(anyhow::anyhow!(
+                    "Value {} outside -{}..={} for column {}",
+                    val,
+                    int_max,
+                    int_max,
+                    name
+                ));
+            }
+            zigzag_encode(val)
+        }
+        ColumnSchema::Decimal { min, max, step, name, .. } => {
+            let val: f64 = raw.parse()
+                .ok()
+                .filter(|v: &f64| v.is_finite())
+                .ok_or_else(|| anyhow::anyhow!("Invalid decimal value '{}' for column {}", raw, name))?;
+            if val < *min || val > *max {
+                return Err(anyhow::anyhow!(
+                    "Value {} outside {}..={} for column {}",
+                    val,
+                    min,
+                    max,
+                    name
+                ));
+            }
+            let max_index = decimal_max_index(*min, *max, *step);
+            (((val - min) / step).round() as u128).min(max_index.into())
+        }
+        ColumnSchema::Bool { true_values, false_values, true_output, false_output, name, .. } => {
+            let matches = |output: &String, spellings: &[String]| {
+                raw.eq_ignore_ascii_case(output) || spellings.iter().any(|s| s.eq_ignore_ascii_case(raw))
+            };
+            if matches(true_output, true_values) {
+                1
+            } else if matches(false_output, false_values) {
+                0
+            } else {
+                return Err(anyhow::anyhow!(
+                    "Invalid boolean value '{}' for column {}",
+                    raw,
+                    name
+                ));
+            }
+        }
+        ColumnSchema::Flags { options, delimiter, name, .. } => {
+            let mut mask = 0u128;
+            for item in raw.split(delimiter.as_str()).map(str::trim).filter(|i| !i.is_empty()) {
+                let bit = options.iter().position(|o| o == item).ok_or_else(|| {
+                    anyhow::anyhow!("Value '{}' not in flag options for column {}", item, name)
+                })?;
+                if mask & (1 << bit) != 0 {
+                    return Err(anyhow::anyhow!("Value '{}' is selected twice for column {}", item, name));
+                }
+                mask |= 1 << bit;
+            }
+            mask
+        }
+        ColumnSchema::Enum { name, case_insensitive, .. } => {
+            let lookup = enum_lookup.expect("enum columns have a lookup");
+            *lookup.get(&enum_key(raw, *case_insensitive))
+                .ok_or_else(|| anyhow::anyhow!(
+                    "Value '{}' not in enum values for column {}",
+                    raw,
+                    name
+                ))?
+        }
+        ColumnSchema::Text { .. } | ColumnSchema::List { .. } | ColumnSchema::Computed { .. } => {
+            unreachable!("handled by encode_with_options")
+        }
+    };
+    Ok(value)
+}
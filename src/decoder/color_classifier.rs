@@ -0,0 +1,188 @@
+use crate::common::color_palette::BgrColor;
+
+/// Palette index picked for one cell and how sure the classifier is about it:
+/// 1.0 when the cell matches its calibration color exactly, 0.0 when it sits
+/// halfway between two calibration colors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Classification {
+    pub index: usize,
+    pub confidence: f64,
+}
+
+/// Nearest-neighbour color model built from the calibration pixels of one image,
+/// so classification happens under the lighting the image was actually captured in.
+/// Each channel is stretched to the range its calibration samples span, which undoes
+/// dim exposure and color casts (e.g. yellow gym lighting crushing the blue channel)
+/// before distances are compared.
+pub struct ColorClassifier {
+    channel_min: [f64; 3],
+    channel_scale: [f64; 3],
+    references: Vec<[f64; 3]>,
+}
+
+impl ColorClassifier {
+    pub fn from_calibration(calibration_bgr: &[BgrColor]) -> Result<Self, anyhow::Error> {
+        if calibration_bgr.len() < 2 {
+            return Err(anyhow::anyhow!(
+                "Need at least 2 calibration colors, got {}",
+                calibration_bgr.len()
+            ));
+        }
+
+        let mut channel_min = [f64::INFINITY; 3];
+        let mut channel_max = [f64::NEG_INFINITY; 3];
+        for &bgr in calibration_bgr {
+            for (c, v) in bgr_channels(bgr).into_iter().enumerate() {
+                channel_min[c] = channel_min[c].min(v);
+                channel_max[c] = channel_max[c].max(v);
+            }
+        }
+
+        // A channel the palette never varies in (e.g. a red/green-only palette's blue)
+        // carries no information, so it is left unscaled rather than amplifying noise.
+        let mut channel_scale = [1.0 / 255.0; 3];
+        for c in 0..3 {
+            let range = channel_max[c] - channel_min[c];
+            if range >= 16.0 {
+                channel_scale[c] = 1.0 / range;
+            }
+        }
+
+        let mut classifier = ColorClassifier {
+            channel_min,
+            channel_scale,
+            references: Vec::with_capacity(calibration_bgr.len()),
+        };
+        classifier.references = calibration_bgr.iter().map(|&bgr| classifier.normalize(bgr)).collect();
+        Ok(classifier)
+    }
+
+    fn normalize(&self, bgr: BgrColor) -> [f64; 3] {
+        let channels = bgr_channels(bgr);
+        [0, 1, 2].map(|c| (channels[c] - self.channel_min[c]) * self.channel_scale[c])
+    }
+
+    pub fn classify(&self, bgr: BgrColor) -> Classification {
+        let color = self.normalize(bgr);
+
+        let mut nearest = (0, f64::INFINITY);
+        let mut second = f64::INFINITY;
+        for (i, reference) in self.references.iter().enumerate() {
+            let d = distance(&color, reference);
+            if d < nearest.1 {
+                second = nearest.1;
+                nearest = (i, d);
+            } else if d < second {
+                second = d;
+            }
+        }
+
+        let confidence = if second > 0.0 {
+            1.0 - nearest.1 / second
+        } else {
+            0.0
+        };
+
+        Classification {
+            index: nearest.0,
+            confidence,
+        }
+    }
+
+    pub fn classify_all(&self, colors: &[BgrColor]) -> Vec<Classification> {
+        colors.iter().map(|&bgr| self.classify(bgr)).collect()
+    }
+}
+
+fn bgr_channels(bgr: BgrColor) -> [f64; 3] {
+    [bgr.0 as f64, bgr.1 as f64, bgr.2 as f64]
+}
+
+fn distance(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::color_palette::palette_to_bgr;
+    use crate::common::constants::get_default_palette_rgb;
+
+    fn palette() -> Vec<BgrColor> {
+        palette_to_bgr(&get_default_palette_rgb())
+    }
+
+    /// Dim, warm light: each channel scaled and offset, blue crushed the most.
+    fn indoor_light(bgr: BgrColor) -> BgrColor {
+        (
+            (bgr.0 as f64 * 0.3 + 12.0) as u8,
+            (bgr.1 as f64 * 0.6 + 20.0) as u8,
+            (bgr.2 as f64 * 0.8 + 30.0) as u8,
+        )
+    }
+
+    fn blend(from: BgrColor, to: BgrColor, t: f64) -> BgrColor {
+        let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+        (mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2))
+    }
+
+    #[test]
+    fn exact_calibration_colors_classify_with_full_confidence() {
+        let palette = palette();
+        let classifier = ColorClassifier::from_calibration(&palette).unwrap();
+        for (i, &bgr) in palette.iter().enumerate() {
+            assert_eq!(classifier.classify(bgr), Classification { index: i, confidence: 1.0 });
+        }
+        assert!(ColorClassifier::from_calibration(&palette[..1]).is_err());
+    }
+
+    #[test]
+    fn dimmed_and_tinted_cells_classify_against_their_calibration() {
+        let lit: Vec<BgrColor> = palette().into_iter().map(indoor_light).collect();
+        let classifier = ColorClassifier::from_calibration(&lit).unwrap();
+        for (i, &bgr) in lit.iter().enumerate() {
+            // A little sensor noise on top of the cast.
+            let noisy = (bgr.0.saturating_add(2), bgr.1.saturating_sub(3), bgr.2.saturating_add(1));
+            let classification = classifier.classify(noisy);
+            assert_eq!(classification.index, i);
+            assert!(classification.confidence > 0.5, "{:?}", classification);
+        }
+    }
+
+    #[test]
+    fn stretch_maps_each_channel_onto_its_calibration_range() {
+        let clean = ColorClassifier::from_calibration(&palette()).unwrap();
+        let lit: Vec<BgrColor> = palette().into_iter().map(indoor_light).collect();
+        let classifier = ColorClassifier::from_calibration(&lit).unwrap();
+
+        // Blue spans 12..=88 under the cast and is stretched back to 0..=1.
+        assert_eq!(classifier.channel_min, [12.0, 20.0, 30.0]);
+        assert_eq!(classifier.normalize((88, 173, 234)), [1.0, 1.0, 1.0]);
+        // The per-channel gain and offset are undone, up to rounding of the cast colors.
+        for (lit_ref, clean_ref) in classifier.references.iter().zip(&clean.references) {
+            assert!(distance(lit_ref, clean_ref) < 0.03, "{:?} vs {:?}", lit_ref, clean_ref);
+        }
+
+        // A channel the calibration barely varies in is not amplified.
+        let flat = ColorClassifier::from_calibration(&[(40, 0, 0), (50, 255, 0), (45, 0, 255)]).unwrap();
+        assert_eq!(flat.channel_scale, [1.0 / 255.0, 1.0 / 255.0, 1.0 / 255.0]);
+        let wide = ColorClassifier::from_calibration(&[(40, 0, 0), (56, 255, 0), (45, 0, 255)]).unwrap();
+        assert_eq!(wide.channel_scale[0], 1.0 / 16.0);
+    }
+
+    #[test]
+    fn cells_between_two_colors_have_low_confidence() {
+        let palette = palette();
+        let classifier = ColorClassifier::from_calibration(&palette).unwrap();
+        // Red and orange-red, blue and purple-blue.
+        for (a, b) in [(0, 1), (8, 9)] {
+            let halfway = classifier.classify(blend(palette[a], palette[b], 0.5));
+            assert!([a, b].contains(&halfway.index));
+            assert!(halfway.confidence < 0.05, "{:?}", halfway);
+
+            let nearer_b = classifier.classify(blend(palette[a], palette[b], 0.6));
+            assert_eq!(nearer_b.index, b);
+            assert!(nearer_b.confidence < 0.4, "{:?}", nearer_b);
+        }
+    }
+}
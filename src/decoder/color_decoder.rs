@@ -0,0 +1,110 @@
+use crate::common::color_palette::calculate_bits_per_pixel;
+
+pub fn decode_byte_from_indices(color_indices: &[usize], num_colors: usize) -> u8 {
+    let bits_per_pixel = calculate_bits_per_pixel(num_colors) as usize;
+    
+    let mut byte_val = 0u16;
+    for (pixel_idx, &color_index) in color_indices.iter().enumerate() {
+        let bit_offset = pixel_idx * bits_per_pixel;
+        if bit_offset < 8 {
+            byte_val |= (color_index as u16) << bit_offset;
+        }
+    }
+    
+    byte_val as u8
+}
+
+pub fn decode_2bytes_from_indices(color_indices: &[usize], num_colors: usize) -> (u8, u8) {
+    let bits_per_pixel = calculate_bits_per_pixel(num_colors) as usize;
+    
+    let mut combined_value = 0u16;
+    for (pixel_idx, &color_index) in color_indices.iter().enumerate() {
+        combined_value |= (color_index as u16) << (pixel_idx * bits_per_pixel);
+    }
+    
+    ((combined_value >> 8) as u8, combined_value as u8)
+}
+
+/// Inverse of `encode_bytes_to_rgb`: turns palette indices back into bytes.
+pub fn decode_indices_to_bytes(color_indices: &[usize], num_colors: usize) -> Vec<u8> {
+    let bits_per_pixel = calculate_bits_per_pixel(num_colors);
+    
+    let mut data_bytes = Vec::new();
+    
+    if 8 % bits_per_pixel == 0 {
+        let pixels_per_2bytes = (16 / bits_per_pixel) as usize;
+        for chunk in color_indices.chunks_exact(pixels_per_2bytes) {
+            let (byte1, byte2) = decode_2bytes_from_indices(chunk, num_colors);
+            data_bytes.push(byte1);
+            data_bytes.push(byte2);
+        }
+    } else {
+        let pixels_per_byte = (8.0 / bits_per_pixel as f64).ceil() as usize;
+        for chunk in color_indices.chunks_exact(pixels_per_byte) {
+            data_bytes.push(decode_byte_from_indices(chunk, num_colors));
+        }
+    }
+    
+    data_bytes
+}
+
+/// Lowest pixel confidence behind each byte, aligned with `decode_indices_to_bytes`.
+pub fn decode_confidences_to_bytes(confidences: &[f64], num_colors: usize) -> Vec<f64> {
+    let bits_per_pixel = calculate_bits_per_pixel(num_colors);
+    let lowest = |chunk: &[f64]| chunk.iter().copied().fold(1.0, f64::min);
+    
+    let mut byte_confidences = Vec::new();
+    
+    if 8 % bits_per_pixel == 0 {
+        let pixels_per_2bytes = (16 / bits_per_pixel) as usize;
+        for chunk in confidences.chunks_exact(pixels_per_2bytes) {
+            let confidence = lowest(chunk);
+            byte_confidences.push(confidence);
+            byte_confidences.push(confidence);
+        }
+    } else {
+        let pixels_per_byte = (8.0 / bits_per_pixel as f64).ceil() as usize;
+        for chunk in confidences.chunks_exact(pixels_per_byte) {
+            byte_confidences.push(lowest(chunk));
+        }
+    }
+    
+    byte_confidences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::color_palette::BgrColor;
+    use crate::encoder::color_encoder::encode_bytes_to_rgb;
+
+    /// Distinct stand-in colors; only their positions matter here.
+    fn palette(num_colors: usize) -> Vec<BgrColor> {
+        (0..num_colors).map(|i| (i as u8, 0, 0)).collect()
+    }
+
+    #[test]
+    fn indices_round_trip_through_the_encoder_for_every_palette_size() {
+        let data: Vec<u8> = (0..=255).rev().chain([0x00, 0xff, 0x5a, 0xa5]).collect();
+        for num_colors in [2, 4, 8, 16] {
+            let indices: Vec<usize> = encode_bytes_to_rgb(&data, &palette(num_colors))
+                .into_iter()
+                .map(|bgr| bgr.0 as usize)
+                .collect();
+            assert_eq!(decode_indices_to_bytes(&indices, num_colors), data, "{} colors", num_colors);
+        }
+    }
+
+    #[test]
+    fn each_byte_takes_its_least_confident_pixel() {
+        // 16 colors: four pixels carry two bytes, so both bytes share the lowest confidence.
+        assert_eq!(
+            decode_confidences_to_bytes(&[1.0, 0.9, 0.2, 1.0, 0.7, 0.8, 0.9, 1.0], 16),
+            [0.2, 0.2, 0.7, 0.7]
+        );
+        // 8 colors: three pixels per byte.
+        assert_eq!(decode_confidences_to_bytes(&[1.0, 0.1, 1.0, 0.6, 0.9, 0.8], 8), [0.1, 0.6]);
+        // Trailing pixels that don't fill a byte are dropped, as in `decode_indices_to_bytes`.
+        assert_eq!(decode_confidences_to_bytes(&[0.5, 0.4, 0.3], 4), Vec::<f64>::new());
+    }
+}
@@ -0,0 +1,418 @@
+use crate::common::expression::{format_computed, Expression};
+use crate::common::packed_header::{read_packed, FLAG_EMBEDDED_SCHEMA};
+use crate::common::schema::{
+    decimal_max_index, get_default_schema, schema_fingerprint, validate_schema, ColumnKind,
+    ColumnSchema,
+};
+use crate::common::schema_codec::schema_from_bytes;
+use crate::common::schema_registry::builtin_schema;
+use crate::common::string_table::{index_bits, string_table_from_bytes};
+use crate::common::values::zigzag_decode;
+use csv::WriterBuilder;
+use std::io::Read;
+
+pub fn write_csv(headers: &[String], rows: &[Vec<String>]) -> Result<Vec<u8>, anyhow::Error> {
+    let mut writer = WriterBuilder::new().from_writer(Vec::new());
+
+    writer.write_record(headers)?;
+    for row in rows {
+        writer.write_record(row)?;
+    }
+
+    writer
+        .into_inner()
+        .map_err(|e| anyhow::anyhow!("Failed to write CSV: {}", e))
+}
+
+/// Reads the byte-aligned bitplanes written by `pack_columnar_bitplanes`, one column at a time.
+pub struct BitplaneReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitplaneReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        BitplaneReader { data, pos: 0 }
+    }
+
+    pub fn read_column(&mut self, bits: u32, num_values: usize) -> Result<Vec<u128>, anyhow::Error> {
+        let mut vals = vec![0u128; num_values];
+        if bits == 0 {
+            return Ok(vals);
+        }
+
+        let bytes_per_plane = num_values.div_ceil(8);
+        for b in (0..bits).rev() {
+            let end = self.pos + bytes_per_plane;
+            if end > self.data.len() {
+                return Err(anyhow::anyhow!(
+                    "Packed data truncated: need {} bytes, only {} available",
+                    end,
+                    self.data.len()
+                ));
+            }
+
+            let plane = &self.data[self.pos..end];
+            for (i, v) in vals.iter_mut().enumerate() {
+                let bit = (plane[i / 8] >> (7 - (i % 8))) & 1;
+                *v |= (bit as u128) << b;
+            }
+            self.pos = end;
+        }
+
+        Ok(vals)
+    }
+}
+
+pub fn unpack_columnar_bitplanes(
+    data: &[u8],
+    bits_by_col: &[u32],
+    num_rows: usize,
+) -> Result<Vec<Vec<u128>>, anyhow::Error> {
+    let mut reader = BitplaneReader::new(data);
+    bits_by_col
+        .iter()
+        .map(|&bits| reader.read_column(bits, num_rows))
+        .collect()
+}
+
+/// Unpacks a packed payload. A schema embedded in the payload takes precedence over
+/// `schema`; otherwise `schema` must be the one used to encode. Without either, the
+/// built-in schema named in the header is used, falling back to the default schema.
+pub fn decode(
+    packed: &[u8],
+    schema: Option<&[ColumnSchema]>,
+) -> Result<(Vec<String>, Vec<Vec<String>>), anyhow::Error> {
+    let (header, body) = read_packed(packed)?;
+    let num_rows = header.num_rows;
+
+    // Only the first zstd frame is payload; legacy images may carry padding after it.
+    let mut data_bytes = Vec::new();
+    zstd::stream::read::Decoder::new(body)?
+        .single_frame()
+        .read_to_end(&mut data_bytes)?;
+
+    let fallback_schema;
+    let embedded_schema;
+    let (schema_to_use, bitplanes) = if header.flags & FLAG_EMBEDDED_SCHEMA != 0 {
+        let (embedded, used) = schema_from_bytes(&data_bytes)?;
+        embedded_schema = embedded;
+        (embedded_schema.as_slice(), &data_bytes[used..])
+    } else {
+        let provided = match schema {
+            Some(s) => s,
+            None => {
+                fallback_schema = match &header.schema_name {
+                    Some(name) => builtin_schema(name)
+                        .map_err(|e| anyhow::anyhow!("Payload was encoded with {}", e))?,
+                    None => get_default_schema(),
+                };
+                &fallback_schema
+            }
+        };
+        (provided, data_bytes.as_slice())
+    };
+
+    validate_schema(schema_to_use)?;
+
+    if let Some(fingerprint) = header.schema_fingerprint {
+        let expected = schema_fingerprint(schema_to_use)?;
+        if fingerprint != expected {
+            return Err(anyhow::anyhow!(
+                "Packed payload was encoded with a different schema (fingerprint {:08x}, expected {:08x})",
+                fingerprint,
+                expected
+            ));
+        }
+    }
+
+    let (strings, bitplanes) = if schema_to_use.iter().any(|c| c.kind() == ColumnKind::Text) {
+        let (strings, used) = string_table_from_bytes(bitplanes)?;
+        (strings, &bitplanes[used..])
+    } else {
+        (Vec::new(), bitplanes)
+    };
+
+    let mut reader = BitplaneReader::new(bitplanes);
+    let mut columns: Vec<Vec<String>> = Vec::with_capacity(schema_to_use.len());
+
+    for col in schema_to_use {
+        if let ColumnSchema::Computed { expression, precision, name } = col {
+            let cells = computed_cells(schema_to_use, &columns, expression, *precision, num_rows)
+                .map_err(|e| anyhow::anyhow!("Column {}: {}", name, e))?;
+            columns.push(cells);
+            continue;
+        }
+        let bits = match col {
+            ColumnSchema::Text { .. } => index_bits(strings.len()),
+            _ => col.bits(),
+        };
+        let presence = if col.nullable() {
+            Some(reader.read_column(1, num_rows)?)
+        } else {
+            None
+        };
+        let num_present = presence
+            .as_ref()
+            .map_or(num_rows, |p| p.iter().filter(|&&bit| bit == 1).count());
+        let vals = reader.read_column(bits, num_present)?;
+
+        let cells = match col {
+            ColumnSchema::List { element, max_length, delimiter, name, .. } => {
+                if let Some(&length) = vals.iter().find(|&&length| length > *max_length as u128) {
+                    return Err(anyhow::anyhow!(
+                        "Decoded list length {} exceeds max_length {} for column {}",
+                        length,
+                        max_length,
+                        name
+                    ));
+                }
+                let total = vals.iter().map(|&length| length as usize).sum();
+                let elements = reader.read_column(element.bits(), total)?;
+                let mut items = decode_cells(element, elements, &strings)?.into_iter();
+                vals.into_iter()
+                    .map(|length| items.by_ref().take(length as usize).collect::<Vec<_>>().join(delimiter))
+                    .collect()
+            }
+            _ => decode_cells(col, vals, &strings)?,
+        };
+
+        let cells = match presence {
+            Some(presence) => {
+                let mut present = cells.into_iter();
+                presence
+                    .into_iter()
+                    .map(|bit| if bit == 1 { present.next().unwrap_or_default() } else { String::new() })
+                    .collect()
+            }
+            None => cells,
+        };
+        columns.push(cells);
+    }
+
+    let headers = schema_to_use.iter().map(|c| c.name().to_string()).collect();
+    let rows = (0..num_rows)
+        .map(|r| columns.iter().map(|c| c[r].clone()).collect())
+        .collect();
+
+    Ok((headers, rows))
+}
+
+/// Turns one column's packed values back into cells. Text cells index `strings`; list
+/// and computed columns are assembled by `decode` instead.
+fn decode_cells(col: &ColumnSchema, vals: Vec<u128>, strings: &[String]) -> Result<Vec<String>, anyhow::Error> {
+    let cells = match col {
+        ColumnSchema::Int { int_max, int_min, name, .. } => vals
+            .into_iter()
+            .map(|offset| {
+                int_min
+                    .checked_add(offset)
+                    .filter(|v| v <= int_max)
+                    .map(|v| v.to_string())
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "Decoded offset {} from int_min {} exceeds int_max {} for column {}",
+                            offset,
+                            int_min,
+                            int_max,
+                            name
+                        )
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        ColumnSchema::Sint { int_max, name, .. } => vals
+            .into_iter()
+            .map(|code| {
+                let v = zigzag_decode(code);
+                if v.unsigned_abs() > *int_max {
+                    return Err(anyhow::anyhow!(
+                        "Decoded value {} outside -{}..={} for column {}",
+                        v,
+                        int_max,
+                        int_max,
+                        name
+                    ));
+                }
+                Ok(v.to_string())
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        ColumnSchema::Decimal { min, max, step, precision, name, .. } => {
+            let max_index = decimal_max_index(*min, *max, *step).into();
+            vals.into_iter()
+                .map(|index| {
+                    if index > max_index {
+                        return Err(anyhow::anyhow!(
+                            "Decoded decimal index {} exceeds {} for column {}",
+                            index,
+                            max_index,
+                            name
+                        ));
+                    }
+                    let text = format!("{:.*}", *precision as usize, min + index as f64 * step);
+                    // Rounding error just below zero would otherwise print as "-0.00".
+                    match text.strip_prefix('-') {
+                        Some(rest) if rest.chars().all(|c| c == '0' || c == '.') => Ok(rest.to_string()),
+                        _ => Ok(text),
+                    }
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        }
+        ColumnSchema::Text { name, .. } => vals
+            .into_iter()
+            .map(|v| {
+                strings.get(v as usize).cloned().ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Decoded string index {} out of range for column {}",
+                        v,
+                        name
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        ColumnSchema::Bool { true_output, false_output, .. } => vals
+            .into_iter()
+            .map(|v| if v == 1 { true_output.clone() } else { false_output.clone() })
+            .collect(),
+        ColumnSchema::Flags { options, delimiter, .. } => vals
+            .into_iter()
+            .map(|mask| {
+                options
+                    .iter()
+                    .enumerate()
+                    .filter(|&(i, _)| mask >> i & 1 == 1)
+                    .map(|(_, option)| option.as_str())
+                    .collect::<Vec<_>>()
+                    .join(delimiter)
+            })
+            .collect(),
+        ColumnSchema::Enum { values, name, .. } => vals
+            .into_iter()
+            .map(|v| {
+                values.get(v as usize).cloned().ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Decoded enum index {} out of range for column {}",
+                        v,
+                        name
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        ColumnSchema::List { .. } | ColumnSchema::Computed { .. } => unreachable!("assembled by decode"),
+    };
+    Ok(cells)
+}
+
+/// Evaluates a computed column over the columns decoded so far, which validation
+/// guarantees include every column the expression reads. Rows where an input is
+/// empty or the result is not finite get an empty cell.
+fn computed_cells(
+    schema: &[ColumnSchema],
+    decoded: &[Vec<String>],
+    expression: &str,
+    precision: Option<u32>,
+    num_rows: usize,
+) -> Result<Vec<String>, anyhow::Error> {
+    let parsed = Expression::parse(expression)?;
+    let lookup = |row: usize, name: &str| -> Option<f64> {
+        let idx = schema[..decoded.len()].iter().position(|col| col.name() == name)?;
+        let cell = decoded[idx][row].as_str();
+        match &schema[idx] {
+            _ if cell.is_empty() => None,
+            ColumnSchema::Bool { true_output, .. } => Some((cell == true_output) as u8 as f64),
+            _ => cell.parse().ok(),
+        }
+    };
+    Ok((0..num_rows)
+        .map(|row| {
+            parsed
+                .evaluate(&|name| lookup(row, name))
+                .map_or_else(String::new, |value| format_computed(value, precision))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::schema::load_schema;
+    use crate::encoder::data_packer::{encode, encode_with_options, PackOptions};
+
+    const SCHEMA: &[u8] = br#"[
+        { "name": "Team", "kind": "int", "int_max": 9999 },
+        { "name": "Offset", "kind": "int", "int_min": 1000, "int_max": 1100, "nullable": true },
+        { "name": "Serial", "kind": "int", "bits": 128 },
+        { "name": "Delta", "kind": "sint", "int_max": 50 },
+        { "name": "Wide", "kind": "sint", "bits": 128 },
+        { "name": "Climb", "kind": "enum", "values": ["None", "Park", "Deep"],
+          "aliases": { "no": "None" }, "case_insensitive": true, "nullable": true },
+        { "name": "Cycle", "kind": "decimal", "min": -1.5, "max": 30, "step": 0.25 },
+        { "name": "Died", "kind": "bool", "nullable": true },
+        { "name": "Notes", "kind": "text", "max_length": 40, "nullable": true },
+        { "name": "Pickup", "kind": "flags", "options": ["Floor", "Station"] },
+        { "name": "Ratio", "kind": "computed", "expression": "Team / Delta", "precision": 2 },
+        { "name": "Cycles", "kind": "list", "element": { "kind": "decimal", "min": 0, "max": 30, "step": 0.1 },
+          "max_length": 4 },
+        { "name": "Spots", "kind": "list", "element": { "kind": "enum", "values": ["A", "B"] }, "max_length": 3 }
+    ]"#;
+
+    const HEADERS: &str = "Team,Offset,Serial,Delta,Wide,Climb,Cycle,Died,Notes,Pickup,Cycles,Spots";
+
+    fn table(lines: &[&str]) -> Vec<Vec<String>> {
+        lines.iter().map(|line| line.split(',').map(str::to_string).collect()).collect()
+    }
+
+    fn round_trip(rows: &[&str], options: &PackOptions) -> (Vec<String>, Vec<Vec<String>>) {
+        let schema = load_schema(Some(SCHEMA)).unwrap();
+        let headers = &table(&[HEADERS])[0];
+        let packed = encode_with_options(headers, &table(rows), Some(&schema), options).unwrap().packed;
+        let decode_schema = if options.embed_schema { None } else { Some(schema.as_slice()) };
+        decode(&packed, decode_schema).unwrap()
+    }
+
+    #[test]
+    fn every_column_kind_round_trips() {
+        let (headers, rows) = round_trip(
+            &[
+                "254,1000,340282366920938463463374607431768211455,-50,-170141183460469231731687303715884105727,No,30,yes,hi,Floor|Station,0.5|29.9,A|B|A",
+                "9999,,0,7,170141183460469231731687303715884105727,,-1.5,,,,,",
+                "0,1100,12345678901234567890123,0,0,DEEP,2.3,0,a b,Station,,B",
+            ],
+            &PackOptions::default(),
+        );
+        assert_eq!(
+            headers.join(","),
+            "Team,Offset,Serial,Delta,Wide,Climb,Cycle,Died,Notes,Pickup,Ratio,Cycles,Spots"
+        );
+        assert_eq!(
+            rows,
+            table(&[
+                "254,1000,340282366920938463463374607431768211455,-50,-170141183460469231731687303715884105727,None,30.00,true,hi,Floor|Station,-5.08,0.5|29.9,A|B|A",
+                "9999,,0,7,170141183460469231731687303715884105727,,-1.50,,,,1428.43,,",
+                "0,1100,12345678901234567890123,0,0,Deep,2.25,false,a b,Station,,,B",
+            ])
+        );
+    }
+
+    #[test]
+    fn embedded_schema_decodes_without_one() {
+        let rows = ["1,,0,1,0,park,0,true,,Floor,1,A"];
+        let options = PackOptions { embed_schema: true, ..PackOptions::default() };
+        assert_eq!(round_trip(&rows, &options), round_trip(&rows, &PackOptions::default()));
+    }
+
+    #[test]
+    fn default_schema_round_trips() {
+        let schema = crate::common::schema::get_default_schema();
+        let headers: Vec<String> = schema.iter().map(|col| col.name().to_string()).collect();
+        let row: Vec<String> = schema
+            .iter()
+            .map(|col| match col {
+                ColumnSchema::Enum { values, .. } => values[values.len() - 1].clone(),
+                ColumnSchema::Int { int_max, .. } => int_max.to_string(),
+                _ => "0".to_string(),
+            })
+            .collect();
+        let packed = encode(&headers, std::slice::from_ref(&row), None).unwrap();
+        assert_eq!(decode(&packed, None).unwrap(), (headers, vec![row]));
+    }
+}
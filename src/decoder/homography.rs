@@ -0,0 +1,201 @@
+pub type Point = (f64, f64);
+
+/// Projective transform between two planes, stored row-major with `m[8] == 1`.
+#[derive(Debug, Clone, Copy)]
+pub struct Homography {
+    m: [f64; 9],
+}
+
+impl Homography {
+    /// Least-squares fit mapping each `(src, dst)` pair; needs at least four
+    /// correspondences, no three of them collinear.
+    pub fn estimate(correspondences: &[(Point, Point)]) -> Result<Self, anyhow::Error> {
+        if correspondences.len() < 4 {
+            return Err(anyhow::anyhow!(
+                "Homography needs at least 4 point pairs, got {}",
+                correspondences.len()
+            ));
+        }
+
+        let src: Vec<Point> = correspondences.iter().map(|c| c.0).collect();
+        let dst: Vec<Point> = correspondences.iter().map(|c| c.1).collect();
+        let src_norm = Normalization::new(&src);
+        let dst_norm = Normalization::new(&dst);
+
+        // Normal equations of the DLT system with h33 fixed to 1.
+        let mut ata = [[0.0f64; 8]; 8];
+        let mut atb = [0.0f64; 8];
+        for (s, d) in src.iter().zip(&dst) {
+            let (x, y) = src_norm.apply(*s);
+            let (u, v) = dst_norm.apply(*d);
+            let rows = [
+                ([x, y, 1.0, 0.0, 0.0, 0.0, -u * x, -u * y], u),
+                ([0.0, 0.0, 0.0, x, y, 1.0, -v * x, -v * y], v),
+            ];
+            for (a, b) in rows {
+                for i in 0..8 {
+                    for j in 0..8 {
+                        ata[i][j] += a[i] * a[j];
+                    }
+                    atb[i] += a[i] * b;
+                }
+            }
+        }
+
+        let h = solve_linear(ata, atb)
+            .ok_or_else(|| anyhow::anyhow!("Degenerate point configuration for homography"))?;
+        let normalized = [h[0], h[1], h[2], h[3], h[4], h[5], h[6], h[7], 1.0];
+
+        let m = mat_mul(&mat_mul(&dst_norm.inverse(), &normalized), &src_norm.matrix());
+        if m[8].abs() < 1e-12 {
+            return Err(anyhow::anyhow!("Degenerate point configuration for homography"));
+        }
+        Ok(Homography {
+            m: m.map(|v| v / m[8]),
+        })
+    }
+
+    pub fn apply(&self, p: Point) -> Point {
+        let m = &self.m;
+        let w = m[6] * p.0 + m[7] * p.1 + m[8];
+        (
+            (m[0] * p.0 + m[1] * p.1 + m[2]) / w,
+            (m[3] * p.0 + m[4] * p.1 + m[5]) / w,
+        )
+    }
+}
+
+/// Hartley normalization: centroid to the origin, mean distance sqrt(2).
+struct Normalization {
+    cx: f64,
+    cy: f64,
+    scale: f64,
+}
+
+impl Normalization {
+    fn new(points: &[Point]) -> Self {
+        let n = points.len() as f64;
+        let cx = points.iter().map(|p| p.0).sum::<f64>() / n;
+        let cy = points.iter().map(|p| p.1).sum::<f64>() / n;
+        let mean_dist = points
+            .iter()
+            .map(|p| ((p.0 - cx).powi(2) + (p.1 - cy).powi(2)).sqrt())
+            .sum::<f64>()
+            / n;
+        let scale = if mean_dist > 1e-12 {
+            std::f64::consts::SQRT_2 / mean_dist
+        } else {
+            1.0
+        };
+        Normalization { cx, cy, scale }
+    }
+
+    fn apply(&self, p: Point) -> Point {
+        ((p.0 - self.cx) * self.scale, (p.1 - self.cy) * self.scale)
+    }
+
+    fn matrix(&self) -> [f64; 9] {
+        let s = self.scale;
+        [s, 0.0, -s * self.cx, 0.0, s, -s * self.cy, 0.0, 0.0, 1.0]
+    }
+
+    fn inverse(&self) -> [f64; 9] {
+        let s = 1.0 / self.scale;
+        [s, 0.0, self.cx, 0.0, s, self.cy, 0.0, 0.0, 1.0]
+    }
+}
+
+fn mat_mul(a: &[f64; 9], b: &[f64; 9]) -> [f64; 9] {
+    let mut out = [0.0; 9];
+    for r in 0..3 {
+        for c in 0..3 {
+            out[r * 3 + c] = (0..3).map(|k| a[r * 3 + k] * b[k * 3 + c]).sum();
+        }
+    }
+    out
+}
+
+/// Gaussian elimination with partial pivoting.
+fn solve_linear<const N: usize>(mut a: [[f64; N]; N], mut b: [f64; N]) -> Option<[f64; N]> {
+    for col in 0..N {
+        let pivot = (col..N).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+
+        let pivot_row = a[col];
+        for row in col + 1..N {
+            let factor = a[row][col] / pivot_row[col];
+            for (v, p) in a[row][col..].iter_mut().zip(&pivot_row[col..]) {
+                *v -= factor * p;
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+
+    let mut x = [0.0; N];
+    for row in (0..N).rev() {
+        let sum: f64 = (row + 1..N).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - sum) / a[row][row];
+    }
+    Some(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE: [Point; 4] = [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)];
+
+    fn assert_close(a: Point, b: Point) {
+        assert!((a.0 - b.0).abs() < 1e-6 && (a.1 - b.1).abs() < 1e-6, "{:?} != {:?}", a, b);
+    }
+
+    /// A fixed projective map with a non-trivial perspective row.
+    fn projective(p: Point) -> Point {
+        let w = 0.002 * p.0 - 0.003 * p.1 + 1.0;
+        ((1.2 * p.0 + 0.3 * p.1 + 5.0) / w, (-0.2 * p.0 + 0.9 * p.1 + 7.0) / w)
+    }
+
+    #[test]
+    fn identity_correspondences_give_the_identity() {
+        let h = Homography::estimate(&SQUARE.map(|p| (p, p))).unwrap();
+        for p in [(0.0, 0.0), (3.5, 7.25), (-20.0, 40.0), (100.0, 100.0)] {
+            assert_close(h.apply(p), p);
+        }
+    }
+
+    #[test]
+    fn recovers_a_known_projective_map() {
+        let h = Homography::estimate(&SQUARE.map(|p| (p, projective(p)))).unwrap();
+        for p in [(5.0, 5.0), (2.0, 8.0), (-3.0, 12.0), (25.0, -4.0)] {
+            assert_close(h.apply(p), projective(p));
+        }
+
+        // Extra exact correspondences leave the least-squares fit unchanged.
+        let many: Vec<(Point, Point)> = (0..5)
+            .flat_map(|i| (0..5).map(move |j| (i as f64 * 4.0, j as f64 * 3.0)))
+            .map(|p| (p, projective(p)))
+            .collect();
+        let h = Homography::estimate(&many).unwrap();
+        assert_close(h.apply((7.0, 1.0)), projective((7.0, 1.0)));
+    }
+
+    #[test]
+    fn degenerate_configurations_are_rejected() {
+        let too_few = [((0.0, 0.0), (0.0, 0.0)), ((1.0, 0.0), (1.0, 0.0)), ((0.0, 1.0), (0.0, 1.0))];
+        assert!(Homography::estimate(&too_few).is_err());
+
+        let collinear = [(0.0, 0.0), (1.0, 1.0), (2.0, 2.0), (5.0, 5.0)];
+        assert!(Homography::estimate(&collinear.map(|p| (p, p))).is_err());
+        assert!(Homography::estimate(&SQUARE.iter().copied().zip(collinear).collect::<Vec<_>>()).is_err());
+
+        let three_collinear = [(0.0, 0.0), (5.0, 0.0), (10.0, 0.0), (0.0, 10.0)];
+        assert!(Homography::estimate(&three_collinear.map(|p| (p, projective(p)))).is_err());
+
+        let coincident = [(3.0, 3.0); 4];
+        assert!(Homography::estimate(&SQUARE.iter().copied().zip(coincident).collect::<Vec<_>>()).is_err());
+    }
+}
@@ -0,0 +1,305 @@
+use crate::common::apriltag::get_april_tag_size;
+use crate::common::color_palette::BgrColor;
+use crate::common::data_regions::{get_data_pixel_coords, ImageLayout};
+use crate::common::fec::{correct_errors, protected_length};
+use crate::common::header_strip::{header_cells, header_cells_from_tag, ImageHeader, HEADER_ROWS};
+use crate::decoder::color_classifier::{Classification, ColorClassifier};
+use crate::decoder::color_decoder::{decode_confidences_to_bytes, decode_indices_to_bytes};
+use crate::decoder::homography::{Homography, Point};
+use crate::decoder::sampling_grid::SamplingGrid;
+use crate::decoder::tag_detector::TagDetection;
+use image::RgbImage;
+
+/// Minimum luminance difference between tag 0's black border and the white gap
+/// next to it before the header strip is trusted.
+const MIN_HEADER_CONTRAST: f64 = 20.0;
+
+/// Bytes with a pixel classified below this confidence are handed to the
+/// Reed-Solomon decoder as erasures.
+const ERASURE_CONFIDENCE: f64 = 0.25;
+
+fn luminance(bgr: BgrColor) -> f64 {
+    (bgr.0 as f64 + bgr.1 as f64 + bgr.2 as f64) / 3.0
+}
+
+fn pixel_bgr(image: &RgbImage, x: usize, y: usize) -> BgrColor {
+    let rgb = image.get_pixel(x as u32, y as u32);
+    (rgb[2], rgb[1], rgb[0])
+}
+
+/// Finds tag 0 in a lossless image by walking the diagonal to its top-left corner,
+/// then measuring its black top border. Returns `(padding, module_size)`.
+fn locate_tag_origin(image: &RgbImage) -> Result<(usize, usize), anyhow::Error> {
+    let is_dark = |x: usize, y: usize| luminance(pixel_bgr(image, x, y)) < 128.0;
+    let width = image.width() as usize;
+    let height = image.height() as usize;
+
+    let start = (0..width.min(height))
+        .find(|&i| is_dark(i, i))
+        .ok_or_else(|| anyhow::anyhow!("Could not find AprilTag 0 in image"))?;
+    let run = (start..width).take_while(|&x| is_dark(x, start)).count();
+
+    let tag_size = get_april_tag_size();
+    if run % tag_size != 0 || start % (run / tag_size) != 0 {
+        return Err(anyhow::anyhow!(
+            "AprilTag 0 border does not line up with a whole number of modules"
+        ));
+    }
+    let module_size = run / tag_size;
+    Ok((start / module_size, module_size))
+}
+
+/// Reads the header strip of a lossless copy of an encoded image.
+pub fn read_image_header(image: &RgbImage) -> Result<ImageHeader, anyhow::Error> {
+    let (padding, module_size) = locate_tag_origin(image)?;
+
+    let bits: Vec<bool> = header_cells(padding)
+        .into_iter()
+        .map(|(row, col)| {
+            let center = |m: usize| m * module_size + module_size / 2;
+            luminance(pixel_bgr(image, center(col), center(row))) < 128.0
+        })
+        .collect();
+    let header = ImageHeader::from_bits(&bits)?;
+
+    let layout = &header.layout;
+    if header.module_size != module_size || layout.padding != padding {
+        return Err(anyhow::anyhow!(
+            "Header strip disagrees with the tag geometry (module size {} vs {}, padding {} vs {})",
+            header.module_size,
+            module_size,
+            layout.padding,
+            padding
+        ));
+    }
+    if image.width() as usize != layout.image_width * module_size
+        || image.height() as usize != layout.image_height * module_size
+    {
+        return Err(anyhow::anyhow!(
+            "Image is {}x{} pixels but its header describes {}x{} modules of {} pixels",
+            image.width(),
+            image.height(),
+            layout.image_width,
+            layout.image_height,
+            module_size
+        ));
+    }
+    Ok(header)
+}
+
+/// Reads the calibration pixels that `create_encoded_image` places at the end of the data regions.
+pub fn read_calibration_colors(image: &RgbImage) -> Result<Vec<BgrColor>, anyhow::Error> {
+    let header = read_image_header(image)?;
+    let colors = read_data_colors(image, &header);
+    split_calibration(&colors, header.num_colors()).map(|(_, calibration)| calibration.to_vec())
+}
+
+fn read_data_colors(image: &RgbImage, header: &ImageHeader) -> Vec<BgrColor> {
+    let module_size = header.module_size;
+    get_data_pixel_coords(&header.layout)
+        .into_iter()
+        .map(|(row, col)| {
+            pixel_bgr(
+                image,
+                col * module_size + module_size / 2,
+                row * module_size + module_size / 2,
+            )
+        })
+        .collect()
+}
+
+/// Splits data pixel colors into `(data, calibration)`.
+fn split_calibration(
+    colors: &[BgrColor],
+    num_colors: usize,
+) -> Result<(&[BgrColor], &[BgrColor]), anyhow::Error> {
+    if colors.len() < num_colors {
+        return Err(anyhow::anyhow!(
+            "Image has {} data pixels, fewer than the {} calibration pixels expected",
+            colors.len(),
+            num_colors
+        ));
+    }
+    Ok(colors.split_at(colors.len() - num_colors))
+}
+
+/// Classifies every data color with a model built from the trailing calibration colors.
+pub fn classify_data_colors(
+    colors: &[BgrColor],
+    num_colors: usize,
+) -> Result<Vec<Classification>, anyhow::Error> {
+    let (data, calibration_bgr) = split_calibration(colors, num_colors)?;
+    let classifier = ColorClassifier::from_calibration(calibration_bgr)?;
+    Ok(classifier.classify_all(data))
+}
+
+/// Returns the payload bytes carried by the data colors, with any error correction applied.
+fn decode_data_colors(colors: &[BgrColor], header: &ImageHeader) -> Result<Vec<u8>, anyhow::Error> {
+    let num_colors = header.num_colors();
+    let classifications = classify_data_colors(colors, num_colors)?;
+    let color_indices: Vec<usize> = classifications.iter().map(|c| c.index).collect();
+    let confidences: Vec<f64> = classifications.iter().map(|c| c.confidence).collect();
+
+    let data_bytes = decode_indices_to_bytes(&color_indices, num_colors);
+    let bytes_needed = protected_length(header.payload_length, header.ecc_level);
+    if data_bytes.len() < bytes_needed {
+        return Err(anyhow::anyhow!(
+            "Image carries {} bytes, fewer than the {} bytes its header announces",
+            data_bytes.len(),
+            bytes_needed
+        ));
+    }
+
+    let suspect: Vec<bool> = decode_confidences_to_bytes(&confidences, num_colors)
+        .into_iter()
+        .map(|c| c < ERASURE_CONFIDENCE)
+        .collect();
+    correct_errors(
+        &data_bytes[..bytes_needed],
+        header.payload_length,
+        header.ecc_level,
+        &suspect,
+    )
+}
+
+/// Inverse of `create_encoded_image` for a lossless copy of the generated image.
+pub fn read_encoded_image(image: &RgbImage) -> Result<Vec<u8>, anyhow::Error> {
+    let header = read_image_header(image)?;
+    let colors = read_data_colors(image, &header);
+    decode_data_colors(&colors, &header)
+}
+
+/// Average photo color over the middle of module `(row, col)`.
+fn average_photo_color(photo: &RgbImage, to_photo: impl Fn(Point) -> Point, row: usize, col: usize) -> BgrColor {
+    let max_x = photo.width().saturating_sub(1) as f64;
+    let max_y = photo.height().saturating_sub(1) as f64;
+
+    let mut sum = [0u32; 3];
+    let mut count = 0;
+    for du in [-0.25, 0.0, 0.25] {
+        for dv in [-0.25, 0.0, 0.25] {
+            let (x, y) = to_photo((col as f64 + 0.5 + du, row as f64 + 0.5 + dv));
+            let rgb = photo.get_pixel(x.clamp(0.0, max_x) as u32, y.clamp(0.0, max_y) as u32);
+            for (s, v) in sum.iter_mut().zip(rgb.0) {
+                *s += v as u32;
+            }
+            count += 1;
+        }
+    }
+    (
+        (sum[2] / count) as u8,
+        (sum[1] / count) as u8,
+        (sum[0] / count) as u8,
+    )
+}
+
+/// Reads the header strip next to tag 0 in a camera photo. Only tag 0 is used, so
+/// this works before the image size (and therefore the full grid) is known.
+pub fn read_photo_header(photo: &RgbImage, tag_0: &TagDetection) -> Result<ImageHeader, anyhow::Error> {
+    let size = get_april_tag_size() as f64;
+    let module_corners = [(0.0, 0.0), (size, 0.0), (size, size), (0.0, size)];
+    let tag_map = Homography::estimate(&module_corners.into_iter().zip(tag_0.corners).collect::<Vec<_>>())?;
+    let cell_luminance =
+        |row: usize, col: usize| luminance(average_photo_color(photo, |p| tag_map.apply(p), row, col));
+
+    // The tag's black border and the blank column between tag and strip give the
+    // local black and white levels.
+    let tag_size = get_april_tag_size();
+    let border: Vec<f64> = (0..tag_size)
+        .flat_map(|row| (0..tag_size).map(move |col| (row, col)))
+        .filter(|&(row, col)| row == 0 || col == 0 || row == tag_size - 1 || col == tag_size - 1)
+        .map(|(row, col)| cell_luminance(row, col))
+        .collect();
+    let black = border.iter().sum::<f64>() / border.len() as f64;
+    let white = (0..HEADER_ROWS).map(|row| cell_luminance(row, tag_size)).sum::<f64>() / HEADER_ROWS as f64;
+    if white - black < MIN_HEADER_CONTRAST {
+        return Err(anyhow::anyhow!("Header strip is too low-contrast to read"));
+    }
+
+    let threshold = (black + white) / 2.0;
+    let bits: Vec<bool> = header_cells_from_tag()
+        .into_iter()
+        .map(|(row, col)| cell_luminance(row, col) < threshold)
+        .collect();
+    ImageHeader::from_bits(&bits)
+}
+
+/// Average photo color over the middle of every data pixel, in `get_data_pixel_coords` order.
+pub fn sample_photo_colors(photo: &RgbImage, grid: &SamplingGrid, layout: &ImageLayout) -> Vec<BgrColor> {
+    get_data_pixel_coords(layout)
+        .into_iter()
+        .map(|(row, col)| average_photo_color(photo, |p| grid.module_to_photo(p), row, col))
+        .collect()
+}
+
+/// Reads the payload bytes out of a camera photo of an encoded image.
+pub fn read_encoded_photo(
+    photo: &RgbImage,
+    grid: &SamplingGrid,
+    header: &ImageHeader,
+) -> Result<Vec<u8>, anyhow::Error> {
+    let colors = sample_photo_colors(photo, grid, &header.layout);
+    decode_data_colors(&colors, header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{encode_csv_to_image_with_options, EncodeOptions};
+    use crate::common::fec::EccLevel;
+
+    fn blend(from: BgrColor, to: BgrColor, t: f64) -> BgrColor {
+        let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+        (mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2))
+    }
+
+    #[test]
+    fn low_confidence_cells_become_erasures() {
+        let options = EncodeOptions { ecc_level: EccLevel::High, ..EncodeOptions::default() };
+        let result = encode_csv_to_image_with_options(
+            b"team,score\n254,87\n1678,112\n971,45\n118,0\n2056,130\n",
+            Some(br#"[{ "name": "team", "kind": "int", "int_max": 9999 },
+                      { "name": "score", "kind": "int", "int_max": 255 }]"#),
+            None,
+            &options,
+        )
+        .unwrap();
+        let image = image::load_from_memory(&result.image_bytes).unwrap().to_rgb8();
+        let header = read_image_header(&image).unwrap();
+        let colors = read_data_colors(&image, &header);
+        let num_colors = header.num_colors();
+        assert_eq!(num_colors, 16);
+        let (_, calibration) = split_calibration(&colors, num_colors).unwrap();
+        let indices: Vec<usize> = classify_data_colors(&colors, num_colors)
+            .unwrap()
+            .iter()
+            .map(|c| c.index)
+            .collect();
+
+        // One block; its parity corrects nsym / 2 errors or nsym erasures.
+        let nsym = protected_length(header.payload_length, header.ecc_level) - header.payload_length;
+        assert!(nsym > 4 && 2 * nsym <= colors.len());
+        // Damage both bytes of nsym / 2 - 1 pixel groups (four 4-bit pixels carry two bytes):
+        // nsym - 2 wrong bytes, more than the parity can find on its own.
+        let smudge = |t: f64| {
+            let mut smudged = colors.clone();
+            for group in 0..nsym / 2 - 1 {
+                for pixel in [4 * group, 4 * group + 2] {
+                    let wrong = (indices[pixel] + 1) % num_colors;
+                    smudged[pixel] = blend(calibration[indices[pixel]], calibration[wrong], t);
+                }
+            }
+            smudged
+        };
+        let expected = decode_data_colors(&colors, &header).unwrap();
+
+        // Most of the way to a neighbouring color: misread, but below ERASURE_CONFIDENCE.
+        let unsure = smudge(0.55);
+        let classifications = classify_data_colors(&unsure, num_colors).unwrap();
+        assert!(classifications[0].index != indices[0] && classifications[0].confidence < ERASURE_CONFIDENCE);
+        assert_eq!(decode_data_colors(&unsure, &header).unwrap(), expected);
+
+        // The same damage read with full confidence is beyond the parity.
+        assert!(decode_data_colors(&smudge(1.0), &header).is_err());
+    }
+}
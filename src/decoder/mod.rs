@@ -0,0 +1,7 @@
+pub mod color_classifier;
+pub mod color_decoder;
+pub mod data_unpacker;
+pub mod homography;
+pub mod image_reader;
+pub mod sampling_grid;
+pub mod tag_detector;
@@ -0,0 +1,143 @@
+use crate::common::apriltag::{get_april_tag_origin, get_april_tag_size};
+use crate::common::data_regions::{get_data_pixel_coords, ImageLayout};
+use crate::decoder::homography::{Homography, Point};
+use crate::decoder::tag_detector::TagDetection;
+
+/// Maps module coordinates of an encoded image (x = column, y = row, pixel edges
+/// on integers) into photo coordinates.
+pub struct SamplingGrid {
+    homography: Homography,
+    /// `(module point, photo residual)` at each tag corner, used to bend the
+    /// homography towards the tags where the capture is not perfectly planar.
+    residuals: Vec<(Point, Point)>,
+}
+
+/// Outer corners of a corner tag in module coordinates: top-left, top-right,
+/// bottom-right, bottom-left.
+pub fn tag_module_corners(tag_id: u32, layout: &ImageLayout) -> Result<[Point; 4], anyhow::Error> {
+    let (row, col) = get_april_tag_origin(tag_id, layout.image_width, layout.image_height, layout.padding)?;
+    let size = get_april_tag_size() as f64;
+    let (x, y) = (col as f64, row as f64);
+    Ok([(x, y), (x + size, y), (x + size, y + size), (x, y + size)])
+}
+
+impl SamplingGrid {
+    pub fn from_tags(tags: &[TagDetection; 3], layout: &ImageLayout) -> Result<Self, anyhow::Error> {
+        let mut correspondences = Vec::with_capacity(12);
+        for tag in tags {
+            let module_corners = tag_module_corners(tag.id, layout)?;
+            correspondences.extend(module_corners.into_iter().zip(tag.corners));
+        }
+
+        let homography = Homography::estimate(&correspondences)?;
+        let residuals = correspondences
+            .iter()
+            .map(|&(module, photo)| {
+                let fitted = homography.apply(module);
+                (module, (photo.0 - fitted.0, photo.1 - fitted.1))
+            })
+            .collect();
+
+        Ok(SamplingGrid {
+            homography,
+            residuals,
+        })
+    }
+
+    pub fn module_to_photo(&self, module: Point) -> Point {
+        let sigma = get_april_tag_size() as f64;
+        let (mut dx, mut dy, mut weight) = (0.0, 0.0, 0.0);
+        for &(corner, residual) in &self.residuals {
+            let d2 = (module.0 - corner.0).powi(2) + (module.1 - corner.1).powi(2);
+            let w = (-d2 / (2.0 * sigma * sigma)).exp();
+            dx += w * residual.0;
+            dy += w * residual.1;
+            weight += w;
+        }
+        // Fades to the plain homography away from the tags.
+        let norm = weight.max(1.0);
+
+        let p = self.homography.apply(module);
+        (p.0 + dx / norm, p.1 + dy / norm)
+    }
+
+    /// Photo coordinates of the center of every data pixel, in `get_data_pixel_coords` order.
+    pub fn sample_points(&self, layout: &ImageLayout) -> Vec<Point> {
+        get_data_pixel_coords(layout)
+            .into_iter()
+            .map(|(row, col)| self.module_to_photo((col as f64 + 0.5, row as f64 + 0.5)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LAYOUT: ImageLayout = ImageLayout {
+        image_width: 60,
+        image_height: 48,
+        padding: 4,
+        tag_data_gap: 1,
+        data_padding: 4,
+    };
+
+    /// Photo of the module plane: scaled, shifted and seen in perspective.
+    fn camera(p: Point) -> Point {
+        let w = 0.001 * p.0 + 0.002 * p.1 + 1.0;
+        ((6.0 * p.0 + 1.0 * p.1 + 30.0) / w, (-0.5 * p.0 + 5.5 * p.1 + 40.0) / w)
+    }
+
+    fn tags(photo: impl Fn(Point) -> Point) -> [TagDetection; 3] {
+        [0, 1, 2].map(|id| TagDetection {
+            id,
+            corners: tag_module_corners(id, &LAYOUT).unwrap().map(&photo),
+            hamming: 0,
+        })
+    }
+
+    fn distance(a: Point, b: Point) -> f64 {
+        ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+    }
+
+    #[test]
+    fn tag_corners_follow_the_tag_origins() {
+        assert_eq!(tag_module_corners(0, &LAYOUT).unwrap(), [(4.0, 4.0), (12.0, 4.0), (12.0, 12.0), (4.0, 12.0)]);
+        assert_eq!(tag_module_corners(1, &LAYOUT).unwrap()[1], (56.0, 4.0));
+        assert_eq!(tag_module_corners(2, &LAYOUT).unwrap()[3], (4.0, 44.0));
+        assert!(tag_module_corners(3, &LAYOUT).is_err());
+    }
+
+    #[test]
+    fn planar_photos_map_every_module_through_the_homography() {
+        let grid = SamplingGrid::from_tags(&tags(camera), &LAYOUT).unwrap();
+        let coords = get_data_pixel_coords(&LAYOUT);
+        let points = grid.sample_points(&LAYOUT);
+        assert_eq!(points.len(), coords.len());
+        for (&(row, col), &point) in coords.iter().zip(&points) {
+            let expected = camera((col as f64 + 0.5, row as f64 + 0.5));
+            assert!(distance(point, expected) < 1e-6, "module ({}, {}): {:?} != {:?}", row, col, point, expected);
+        }
+    }
+
+    #[test]
+    fn residuals_bend_the_grid_near_the_tags_only() {
+        // Tag 1 sits a few pixels off the plane the other two tags define.
+        let mut bent = tags(camera);
+        for corner in &mut bent[1].corners {
+            corner.1 += 4.0;
+        }
+        let grid = SamplingGrid::from_tags(&bent, &LAYOUT).unwrap();
+
+        // Closer overall to the detected corners of the bent tag than the homography alone...
+        let (mut bent_error, mut plain_error) = (0.0, 0.0);
+        for (module, photo) in tag_module_corners(1, &LAYOUT).unwrap().into_iter().zip(bent[1].corners) {
+            bent_error += distance(grid.module_to_photo(module), photo);
+            plain_error += distance(grid.homography.apply(module), photo);
+        }
+        assert!(bent_error < plain_error, "{} >= {}", bent_error, plain_error);
+        // ...and back on the plain homography far from every tag.
+        let far = (32.0, 40.0);
+        assert!(distance(grid.module_to_photo(far), grid.homography.apply(far)) < 0.1);
+    }
+}
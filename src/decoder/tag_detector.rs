@@ -0,0 +1,619 @@
+use crate::common::apriltag::{get_april_tag_size, load_april_tag};
+use crate::decoder::homography::{Homography, Point};
+use image::{GrayImage, RgbImage};
+
+/// Tag ids placed by `generate_april_tags_image`: 0 top-left, 1 top-right, 2 bottom-left.
+pub const CORNER_TAG_IDS: [u32; 3] = [0, 1, 2];
+
+/// Largest number of flipped data bits accepted when matching a tag code.
+const MAX_HAMMING: u32 = 2;
+
+/// Pixels darker than the local mean by at least this much count as black.
+const THRESHOLD_OFFSET: u32 = 8;
+
+/// Minimum black/white difference across a candidate's border before it is decoded.
+const MIN_TAG_CONTRAST: f64 = 20.0;
+
+#[derive(Debug, Clone)]
+pub struct TagDetection {
+    pub id: u32,
+    /// Outer corners of the tag's black border in photo pixel coordinates, in the
+    /// tag's own orientation: top-left, top-right, bottom-right, bottom-left.
+    pub corners: [Point; 4],
+    pub hamming: u32,
+}
+
+impl TagDetection {
+    pub fn center(&self) -> Point {
+        let (sx, sy) = self
+            .corners
+            .iter()
+            .fold((0.0, 0.0), |(sx, sy), &(x, y)| (sx + x, sy + y));
+        (sx / 4.0, sy / 4.0)
+    }
+
+    fn area(&self) -> f64 {
+        polygon_area(&self.corners)
+    }
+}
+
+/// Finds the corner tags (ids 0, 1 and 2) in an arbitrary RGB photo, keeping the
+/// best match for each id.
+pub fn detect_april_tags(image: &RgbImage) -> Result<Vec<TagDetection>, anyhow::Error> {
+    let gray = image::imageops::grayscale(image);
+    let codes = CORNER_TAG_IDS
+        .iter()
+        .map(|&id| Ok((id, tag_code(id)?)))
+        .collect::<Result<Vec<_>, anyhow::Error>>()?;
+
+    let dark = threshold_adaptive(&gray);
+    let mut best: Vec<Option<TagDetection>> = vec![None; CORNER_TAG_IDS.len()];
+
+    for boundary in find_dark_components(&dark, gray.width() as usize, gray.height() as usize) {
+        let Some(quad) = fit_quad(&boundary) else {
+            continue;
+        };
+        let quad = refine_edges(&gray, &quad);
+        let Some(detection) = decode_quad(&gray, &quad, &codes) else {
+            continue;
+        };
+
+        let Some(index) = CORNER_TAG_IDS.iter().position(|&id| id == detection.id) else {
+            continue;
+        };
+        let slot = &mut best[index];
+        let better = match slot {
+            None => true,
+            Some(current) => {
+                detection.hamming < current.hamming
+                    || (detection.hamming == current.hamming && detection.area() > current.area())
+            }
+        };
+        if better {
+            *slot = Some(detection);
+        }
+    }
+
+    Ok(best.into_iter().flatten().collect())
+}
+
+/// Like `detect_april_tags` but requires all three corner tags, returned in id order.
+pub fn locate_corner_tags(image: &RgbImage) -> Result<[TagDetection; 3], anyhow::Error> {
+    let detections = detect_april_tags(image)?;
+
+    let find = |id: u32| detections.iter().find(|d| d.id == id).cloned();
+    match (find(0), find(1), find(2)) {
+        (Some(t0), Some(t1), Some(t2)) => Ok([t0, t1, t2]),
+        _ => {
+            let missing: Vec<u32> = CORNER_TAG_IDS
+                .iter()
+                .copied()
+                .filter(|&id| find(id).is_none())
+                .collect();
+            Err(anyhow::anyhow!(
+                "Could not find AprilTag(s) {:?} in image",
+                missing
+            ))
+        }
+    }
+}
+
+/// The tag's data cells (inside the black border) read from the embedded tag image,
+/// row-major, `true` for white.
+fn tag_code(tag_id: u32) -> Result<Vec<bool>, anyhow::Error> {
+    let tag = load_april_tag(tag_id)?;
+    let size = get_april_tag_size() as u32;
+    if tag.width() != size || tag.height() != size {
+        return Err(anyhow::anyhow!(
+            "AprilTag {} is {}x{}, expected {}x{}",
+            tag_id,
+            tag.width(),
+            tag.height(),
+            size,
+            size
+        ));
+    }
+
+    let mut code = Vec::new();
+    for y in 1..size - 1 {
+        for x in 1..size - 1 {
+            code.push(tag.get_pixel(x, y)[0] > 127);
+        }
+    }
+    Ok(code)
+}
+
+fn threshold_adaptive(gray: &GrayImage) -> Vec<bool> {
+    let width = gray.width() as usize;
+    let height = gray.height() as usize;
+    let radius = (width.min(height) / 16).max(7);
+
+    let stride = width + 1;
+    let mut integral = vec![0u64; stride * (height + 1)];
+    for y in 0..height {
+        let mut row_sum = 0u64;
+        for x in 0..width {
+            row_sum += gray.get_pixel(x as u32, y as u32)[0] as u64;
+            integral[(y + 1) * stride + x + 1] = integral[y * stride + x + 1] + row_sum;
+        }
+    }
+
+    let mut dark = vec![false; width * height];
+    for y in 0..height {
+        let y0 = y.saturating_sub(radius);
+        let y1 = (y + radius + 1).min(height);
+        for x in 0..width {
+            let x0 = x.saturating_sub(radius);
+            let x1 = (x + radius + 1).min(width);
+            let sum = integral[y1 * stride + x1] + integral[y0 * stride + x0]
+                - integral[y0 * stride + x1]
+                - integral[y1 * stride + x0];
+            let area = ((y1 - y0) * (x1 - x0)) as u64;
+            let value = gray.get_pixel(x as u32, y as u32)[0] as u64;
+            dark[y * width + x] = (value + THRESHOLD_OFFSET as u64) * area < sum;
+        }
+    }
+
+    dark
+}
+
+/// Flood-fills the dark mask and returns the boundary pixel centers of every
+/// component large enough to be a tag.
+fn find_dark_components(dark: &[bool], width: usize, height: usize) -> Vec<Vec<Point>> {
+    let min_side = get_april_tag_size();
+    let mut visited = vec![false; dark.len()];
+    let mut components = Vec::new();
+    let mut stack = Vec::new();
+
+    for start in 0..dark.len() {
+        if !dark[start] || visited[start] {
+            continue;
+        }
+
+        visited[start] = true;
+        stack.push(start);
+        let mut boundary = Vec::new();
+        let (mut min_x, mut max_x, mut min_y, mut max_y) = (usize::MAX, 0, usize::MAX, 0);
+
+        while let Some(idx) = stack.pop() {
+            let (x, y) = (idx % width, idx / width);
+            min_x = min_x.min(x);
+            max_x = max_x.max(x);
+            min_y = min_y.min(y);
+            max_y = max_y.max(y);
+
+            let neighbors = [
+                (x > 0).then(|| idx - 1),
+                (x + 1 < width).then(|| idx + 1),
+                (y > 0).then(|| idx - width),
+                (y + 1 < height).then(|| idx + width),
+            ];
+
+            let mut on_boundary = false;
+            for neighbor in neighbors {
+                match neighbor {
+                    Some(n) if dark[n] => {
+                        if !visited[n] {
+                            visited[n] = true;
+                            stack.push(n);
+                        }
+                    }
+                    _ => on_boundary = true,
+                }
+            }
+            if on_boundary {
+                boundary.push((x as f64 + 0.5, y as f64 + 0.5));
+            }
+        }
+
+        if max_x + 1 - min_x >= min_side && max_y + 1 - min_y >= min_side {
+            components.push(boundary);
+        }
+    }
+
+    components
+}
+
+fn cross(o: Point, a: Point, b: Point) -> f64 {
+    (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+}
+
+fn polygon_area(points: &[Point]) -> f64 {
+    let n = points.len();
+    let twice: f64 = (0..n)
+        .map(|i| {
+            let (a, b) = (points[i], points[(i + 1) % n]);
+            a.0 * b.1 - b.0 * a.1
+        })
+        .sum();
+    twice / 2.0
+}
+
+fn convex_hull(points: &[Point]) -> Vec<Point> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return sorted;
+    }
+
+    let mut hull: Vec<Point> = Vec::with_capacity(sorted.len() * 2);
+    for pass in 0..2 {
+        let start = hull.len();
+        let iter: Box<dyn Iterator<Item = &Point>> = if pass == 0 {
+            Box::new(sorted.iter())
+        } else {
+            Box::new(sorted.iter().rev())
+        };
+        for &p in iter {
+            while hull.len() >= start + 2 && cross(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0.0 {
+                hull.pop();
+            }
+            hull.push(p);
+        }
+        hull.pop();
+    }
+    hull
+}
+
+/// Fits the outer quadrilateral of a component: hull corners first, then each
+/// edge refined with a line fit through the boundary pixels along it.
+fn fit_quad(boundary: &[Point]) -> Option<[Point; 4]> {
+    let hull = convex_hull(boundary);
+    if hull.len() < 4 || hull.len() > 4096 {
+        return None;
+    }
+
+    let mut diameter = (0, 0, 0.0);
+    for i in 0..hull.len() {
+        for j in i + 1..hull.len() {
+            let d = distance(hull[i], hull[j]);
+            if d > diameter.2 {
+                diameter = (i, j, d);
+            }
+        }
+    }
+    let (p0, p2) = (hull[diameter.0], hull[diameter.1]);
+
+    let mut left = (p0, 0.0);
+    let mut right = (p0, 0.0);
+    for &p in &hull {
+        let side = cross(p0, p2, p) / diameter.2;
+        if side > left.1 {
+            left = (p, side);
+        }
+        if -side > right.1 {
+            right = (p, -side);
+        }
+    }
+    if left.1 < 0.2 * diameter.2 || right.1 < 0.2 * diameter.2 {
+        return None;
+    }
+
+    let mut corners = [p0, left.0, p2, right.0];
+    if polygon_area(&corners) < 0.0 {
+        corners = [p0, right.0, p2, left.0];
+    }
+
+    let quad_area = polygon_area(&corners);
+    if quad_area < 0.85 * polygon_area(&hull).abs() {
+        return None;
+    }
+
+    refine_quad(boundary, &corners)
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+fn refine_quad(boundary: &[Point], corners: &[Point; 4]) -> Option<[Point; 4]> {
+    let centroid = (
+        corners.iter().map(|p| p.0).sum::<f64>() / 4.0,
+        corners.iter().map(|p| p.1).sum::<f64>() / 4.0,
+    );
+
+    // Each edge as (point on line, unit direction), pushed out half a pixel so it
+    // sits on the outer edge of the border pixels rather than their centers.
+    let mut lines = [((0.0, 0.0), (0.0, 0.0)); 4];
+    for i in 0..4 {
+        let (a, b) = (corners[i], corners[(i + 1) % 4]);
+        let length = distance(a, b);
+        if length < 1.0 {
+            return None;
+        }
+        let dir = ((b.0 - a.0) / length, (b.1 - a.1) / length);
+        let tolerance = (0.04 * length).max(0.75);
+
+        let near: Vec<Point> = boundary
+            .iter()
+            .copied()
+            .filter(|&p| {
+                let t = (p.0 - a.0) * dir.0 + (p.1 - a.1) * dir.1;
+                let off = (p.0 - a.0) * dir.1 - (p.1 - a.1) * dir.0;
+                t > 0.1 * length && t < 0.9 * length && off.abs() < tolerance
+            })
+            .collect();
+
+        let (point, direction) = fit_line(&near).unwrap_or((a, dir));
+
+        let mut normal = (direction.1, -direction.0);
+        let to_centroid = (centroid.0 - point.0, centroid.1 - point.1);
+        if normal.0 * to_centroid.0 + normal.1 * to_centroid.1 > 0.0 {
+            normal = (-normal.0, -normal.1);
+        }
+        lines[i] = ((point.0 + 0.5 * normal.0, point.1 + 0.5 * normal.1), direction);
+    }
+
+    let mut refined = [(0.0, 0.0); 4];
+    for i in 0..4 {
+        refined[i] = intersect_lines(lines[(i + 3) % 4], lines[i])?;
+    }
+
+    // Reject refinements that wandered far from the hull corners.
+    for i in 0..4 {
+        let side = distance(corners[i], corners[(i + 1) % 4]);
+        if distance(refined[i], corners[i]) > 0.25 * side {
+            return None;
+        }
+    }
+
+    Some(refined)
+}
+
+/// Total-least-squares line through `points` as (centroid, unit direction).
+fn fit_line(points: &[Point]) -> Option<(Point, Point)> {
+    if points.len() < 2 {
+        return None;
+    }
+
+    let n = points.len() as f64;
+    let cx = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let cy = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let (mut sxx, mut sxy, mut syy) = (0.0, 0.0, 0.0);
+    for &(x, y) in points {
+        sxx += (x - cx) * (x - cx);
+        sxy += (x - cx) * (y - cy);
+        syy += (y - cy) * (y - cy);
+    }
+    if sxx + syy < 1e-9 {
+        return None;
+    }
+
+    let angle = 0.5 * (2.0 * sxy).atan2(sxx - syy);
+    Some(((cx, cy), (angle.cos(), angle.sin())))
+}
+
+fn intersect_lines(l1: (Point, Point), l2: (Point, Point)) -> Option<Point> {
+    let ((p, r), (q, s)) = (l1, l2);
+    let denom = r.0 * s.1 - r.1 * s.0;
+    if denom.abs() < 1e-6 {
+        return None;
+    }
+    let t = ((q.0 - p.0) * s.1 - (q.1 - p.1) * s.0) / denom;
+    Some((p.0 + t * r.0, p.1 + t * r.1))
+}
+
+/// Moves each edge onto the sub-pixel position where the luminance crosses halfway
+/// between the black border and the white quiet zone. Edges without a clean
+/// crossing keep their pixel-level fit.
+fn refine_edges(gray: &GrayImage, quad: &[Point; 4]) -> [Point; 4] {
+    // Below a few pixels per module the profile would reach into the tag's data cells.
+    let module = distance(quad[0], quad[2]) / std::f64::consts::SQRT_2 / get_april_tag_size() as f64;
+    if module < 3.0 {
+        return *quad;
+    }
+
+    let centroid = (
+        quad.iter().map(|p| p.0).sum::<f64>() / 4.0,
+        quad.iter().map(|p| p.1).sum::<f64>() / 4.0,
+    );
+
+    let mut lines = [((0.0, 0.0), (0.0, 0.0)); 4];
+    for i in 0..4 {
+        let (a, b) = (quad[i], quad[(i + 1) % 4]);
+        let length = distance(a, b);
+        let dir = ((b.0 - a.0) / length, (b.1 - a.1) / length);
+        let mut normal = (dir.1, -dir.0);
+        if normal.0 * (centroid.0 - a.0) + normal.1 * (centroid.1 - a.1) > 0.0 {
+            normal = (-normal.0, -normal.1);
+        }
+
+        // Stay within half a module either side so the profile only sees border and quiet zone.
+        let reach = 0.45 * length / get_april_tag_size() as f64;
+        let steps = (reach * 8.0).ceil() as usize;
+
+        let mut crossings = Vec::new();
+        for k in 1..12 {
+            let t = k as f64 / 12.0;
+            let base = (a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1));
+            let at = |s: f64| sample_bilinear(gray, (base.0 + s * normal.0, base.1 + s * normal.1));
+
+            let profile: Option<Vec<(f64, f64)>> = (0..=2 * steps)
+                .map(|j| {
+                    let s = -reach + reach * j as f64 / steps as f64;
+                    at(s).map(|v| (s, v))
+                })
+                .collect();
+            let Some(profile) = profile else {
+                continue;
+            };
+
+            let inside = profile[0].1;
+            let outside = profile[profile.len() - 1].1;
+            if outside - inside < MIN_TAG_CONTRAST {
+                continue;
+            }
+            let mid = (inside + outside) / 2.0;
+            if let Some(w) = profile.windows(2).find(|w| w[0].1 < mid && w[1].1 >= mid) {
+                let ((s0, v0), (s1, v1)) = (w[0], w[1]);
+                let s = s0 + (mid - v0) / (v1 - v0) * (s1 - s0);
+                crossings.push((base.0 + s * normal.0, base.1 + s * normal.1));
+            }
+        }
+
+        lines[i] = if crossings.len() >= 4 {
+            fit_line(&crossings).unwrap_or((a, dir))
+        } else {
+            (a, dir)
+        };
+    }
+
+    let mut refined = *quad;
+    for i in 0..4 {
+        match intersect_lines(lines[(i + 3) % 4], lines[i]) {
+            Some(p) if distance(p, quad[i]) < 0.1 * distance(quad[i], quad[(i + 1) % 4]) => {
+                refined[i] = p;
+            }
+            _ => return *quad,
+        }
+    }
+    refined
+}
+
+fn sample_bilinear(gray: &GrayImage, point: Point) -> Option<f64> {
+    let (width, height) = (gray.width() as f64, gray.height() as f64);
+    if point.0 < 0.0 || point.1 < 0.0 || point.0 > width || point.1 > height || width < 2.0 || height < 2.0 {
+        return None;
+    }
+    // The outer half of the edge pixels reads the edge pixel itself, so a quiet zone
+    // one pixel wide at the image border can still be sampled.
+    let (x, y) = ((point.0 - 0.5).clamp(0.0, width - 1.0), (point.1 - 0.5).clamp(0.0, height - 1.0));
+    let (x0, y0) = ((x.floor() as u32).min(gray.width() - 2), (y.floor() as u32).min(gray.height() - 2));
+    let (fx, fy) = (x - x0 as f64, y - y0 as f64);
+
+    let p = |dx: u32, dy: u32| gray.get_pixel(x0 + dx, y0 + dy)[0] as f64;
+    Some(
+        p(0, 0) * (1.0 - fx) * (1.0 - fy)
+            + p(1, 0) * fx * (1.0 - fy)
+            + p(0, 1) * (1.0 - fx) * fy
+            + p(1, 1) * fx * fy,
+    )
+}
+
+/// Mean luminance around the center of tag cell `(u, v)`, in cell units.
+fn sample_cell(gray: &GrayImage, map: &Homography, u: f64, v: f64) -> Option<f64> {
+    let mut sum = 0.0;
+    for du in [-0.2, 0.0, 0.2] {
+        for dv in [-0.2, 0.0, 0.2] {
+            sum += sample_bilinear(gray, map.apply((u + du, v + dv)))?;
+        }
+    }
+    Some(sum / 9.0)
+}
+
+fn decode_quad(gray: &GrayImage, quad: &[Point; 4], codes: &[(u32, Vec<bool>)]) -> Option<TagDetection> {
+    let size = get_april_tag_size();
+    let sizef = size as f64;
+
+    let mut best: Option<TagDetection> = None;
+    for rotation in 0..4 {
+        let corners = [
+            quad[rotation],
+            quad[(rotation + 1) % 4],
+            quad[(rotation + 2) % 4],
+            quad[(rotation + 3) % 4],
+        ];
+        let map = Homography::estimate(&[
+            ((0.0, 0.0), corners[0]),
+            ((sizef, 0.0), corners[1]),
+            ((sizef, sizef), corners[2]),
+            ((0.0, sizef), corners[3]),
+        ])
+        .ok()?;
+
+        let mut black = Vec::new();
+        let mut white = Vec::new();
+        let mut cells = Vec::new();
+        for row in 0..size {
+            for col in 0..size {
+                let value = sample_cell(gray, &map, col as f64 + 0.5, row as f64 + 0.5)?;
+                if row == 0 || col == 0 || row == size - 1 || col == size - 1 {
+                    black.push(value);
+                } else {
+                    cells.push(value);
+                }
+            }
+        }
+        for i in 0..size {
+            let t = i as f64 + 0.5;
+            for (u, v) in [(t, -0.5), (t, sizef + 0.5), (-0.5, t), (sizef + 0.5, t)] {
+                white.push(sample_cell(gray, &map, u, v)?);
+            }
+        }
+
+        let black_mean = black.iter().sum::<f64>() / black.len() as f64;
+        let white_mean = white.iter().sum::<f64>() / white.len() as f64;
+        if white_mean - black_mean < MIN_TAG_CONTRAST {
+            return None;
+        }
+        let threshold = (black_mean + white_mean) / 2.0;
+        if black.iter().filter(|&&v| v > threshold).count() > 3 {
+            return None;
+        }
+
+        let bits: Vec<bool> = cells.iter().map(|&v| v > threshold).collect();
+        for (id, code) in codes {
+            let hamming = bits.iter().zip(code).filter(|(a, b)| a != b).count() as u32;
+            if hamming <= MAX_HAMMING && best.as_ref().is_none_or(|b| hamming < b.hamming) {
+                best = Some(TagDetection {
+                    id: *id,
+                    corners,
+                    hamming,
+                });
+            }
+        }
+    }
+
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{encode_csv_to_image_with_options, EncodeOptions};
+
+    fn encoded_image() -> RgbImage {
+        let options = EncodeOptions { module_size: 4, ..EncodeOptions::default() };
+        let result = encode_csv_to_image_with_options(
+            b"a\n1\n2\n3\n",
+            Some(br#"[{ "name": "a", "kind": "int", "int_max": 9 }]"#),
+            None,
+            &options,
+        )
+        .unwrap();
+        image::load_from_memory(&result.image_bytes).unwrap().to_rgb8()
+    }
+
+    fn quadrant(image: &RgbImage, tag: &TagDetection) -> (bool, bool) {
+        let (x, y) = tag.center();
+        (x > image.width() as f64 / 2.0, y > image.height() as f64 / 2.0)
+    }
+
+    #[test]
+    fn finds_the_corner_tags_of_an_encoded_image() {
+        let image = encoded_image();
+        let tags = locate_corner_tags(&image).unwrap();
+        assert_eq!(tags.iter().map(|t| t.id).collect::<Vec<_>>(), CORNER_TAG_IDS);
+        assert!(tags.iter().all(|t| t.hamming == 0));
+        assert_eq!(quadrant(&image, &tags[0]), (false, false));
+        assert_eq!(quadrant(&image, &tags[1]), (true, false));
+        assert_eq!(quadrant(&image, &tags[2]), (false, true));
+    }
+
+    #[test]
+    fn finds_the_corner_tags_of_a_rotated_image() {
+        let image = image::imageops::rotate90(&encoded_image());
+        let tags = locate_corner_tags(&image).unwrap();
+        assert_eq!(quadrant(&image, &tags[0]), (true, false));
+        assert_eq!(quadrant(&image, &tags[1]), (true, true));
+        assert_eq!(quadrant(&image, &tags[2]), (false, false));
+    }
+
+    #[test]
+    fn reports_missing_tags() {
+        let blank = RgbImage::from_pixel(120, 120, image::Rgb([255, 255, 255]));
+        let err = locate_corner_tags(&blank).unwrap_err();
+        assert!(err.to_string().contains("[0, 1, 2]"), "{}", err);
+    }
+}
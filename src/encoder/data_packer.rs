@@ -1,4 +1,10 @@
-use crate::common::schema::ColumnSchema;
+use crate::common::packed_header::{write_packed, FLAG_EMBEDDED_SCHEMA};
+use crate::common::rules::{check_rules, Rule};
+use crate::common::schema::{schema_fingerprint, ColumnKind, ColumnSchema};
+use crate::common::schema_codec::schema_to_bytes;
+use crate::common::schema_registry::builtin_schema_name_for;
+use crate::common::string_table::StringTable;
+use crate::common::values::{enum_lookup, normalize_cell, scalar_value};
 use csv::ReaderBuilder;
 use std::collections::HashMap;
 
@@ -23,7 +29,7 @@ pub fn read_csv(csv_bytes: &[u8]) -> Result<(Vec<String>, Vec<Vec<String>>), any
 }
 
 pub fn pack_columnar_bitplanes(
-    values_by_col: &[Vec<u64>],
+    values_by_col: &[Vec<u128>],
     bits_by_col: &[u32],
 ) -> Vec<u8> {
     let mut out = Vec::new();
@@ -58,22 +64,54 @@ pub fn pack_columnar_bitplanes(
     out
 }
 
+/// Options for `encode_with_options`.
+#[derive(Debug, Clone, Default)]
+pub struct PackOptions {
+    /// Store the resolved schema inside the compressed payload so it decodes
+    /// without a schema file.
+    pub embed_schema: bool,
+    /// Cross-column rules from the schema's `rules` section, checked before packing.
+    pub rules: Vec<Rule>,
+}
+
+/// Output of `encode_with_options`.
+#[derive(Debug, Clone)]
+pub struct PackResult {
+    pub packed: Vec<u8>,
+    /// Rows that failed a warning-severity rule; they are packed anyway.
+    pub warnings: Vec<String>,
+}
+
 pub fn encode(
     headers: &[String],
     rows: &[Vec<String>],
     schema: Option<&[ColumnSchema]>,
 ) -> Result<Vec<u8>, anyhow::Error> {
+    encode_with_options(headers, rows, schema, &PackOptions::default()).map(|result| result.packed)
+}
+
+pub fn encode_with_options(
+    headers: &[String],
+    rows: &[Vec<String>],
+    schema: Option<&[ColumnSchema]>,
+    options: &PackOptions,
+) -> Result<PackResult, anyhow::Error> {
     let schema_to_use = match schema {
         Some(s) => s,
         None => {
             let default = crate::common::schema::get_default_schema();
-            return encode(headers, rows, Some(&default));
+            return encode_with_options(headers, rows, Some(&default), options);
         }
     };
     
     crate::common::schema::validate_schema(schema_to_use)?;
     
-    let schema_names: Vec<&str> = schema_to_use.iter().map(|s| s.name()).collect();
+    // Computed columns are filled in by the decoder, so the CSV doesn't need them.
+    let schema_names: Vec<&str> = schema_to_use
+        .iter()
+        .filter(|s| s.kind() != ColumnKind::Computed)
+        .map(|s| s.name())
+        .collect();
     let header_to_csv_idx: HashMap<&str, usize> = headers
         .iter()
         .enumerate()
@@ -93,35 +131,28 @@ pub fn encode(
         ));
     }
     
+    let warnings = check_rules(headers, rows, schema_to_use, &options.rules)?;
+    
     let num_rows = rows.len();
     
-    let mut enum_lookups: Vec<Option<HashMap<String, u64>>> = Vec::new();
-    for col in schema_to_use {
-        match col {
-            ColumnSchema::Enum { values, .. } => {
-                let lookup: HashMap<String, u64> = values
-                    .iter()
-                    .enumerate()
-                    .map(|(i, v)| (v.clone(), i as u64))
-                    .collect();
-                enum_lookups.push(Some(lookup));
-            }
-            ColumnSchema::Int { .. } => {
-                enum_lookups.push(None);
-            }
-        }
-    }
+    let enum_lookups: Vec<Option<HashMap<String, u128>>> = schema_to_use.iter().map(enum_lookup).collect();
     
-    let mut values_by_col: Vec<Vec<u64>> = Vec::new();
+    let mut values_by_col: Vec<Vec<u128>> = Vec::new();
     let mut bits_by_col: Vec<u32> = Vec::new();
+    let mut string_table = StringTable::default();
+    let mut text_cols: Vec<usize> = Vec::new();
+    let mut list_elements: Vec<Vec<u128>> = vec![Vec::new(); schema_to_use.len()];
     
     for (col_idx, col) in schema_to_use.iter().enumerate() {
-        if col.bits() == 0 {
+        // 0-bit columns still go through the checks below, so a row that doesn't
+        // hold the constant fails instead of being silently replaced on decode.
+        if col.kind() == ColumnKind::Computed {
             continue;
         }
         
         let csv_col_idx = header_to_csv_idx[col.name()];
-        let mut col_vals: Vec<u64> = Vec::new();
+        let mut col_vals: Vec<u128> = Vec::new();
+        let mut presence: Vec<u128> = Vec::new();
         
         for row in rows {
             if csv_col_idx >= row.len() {
@@ -132,50 +163,192 @@ pub fn encode(
                 ));
             }
             
-            let raw = row[csv_col_idx].trim().replace('\n', " ").replace('\r', "");
-            let raw = if raw == " " { "" } else { raw.as_str() };
+            let raw = normalize_cell(&row[csv_col_idx]);
+            let raw = raw.as_str();
+            
+            // Nullable columns get a presence plane; values are packed only where present.
+            if col.nullable() {
+                presence.push(!raw.is_empty() as u128);
+                if raw.is_empty() {
+                    continue;
+                }
+            }
             
             let value = match col {
-                ColumnSchema::Int { int_max, name, .. } => {
-                    let val: u64 = raw.to_string().parse()
-                        .map_err(|_| anyhow::anyhow!("Invalid integer value '{}' for column {}", raw, name))?;
-                    if val > *int_max {
+                ColumnSchema::Text { max_length, name, .. } => {
+                    let length = raw.chars().count();
+                    if length > *max_length as usize {
                         return Err(anyhow::anyhow!(
-                            "Value {} exceeds int_max {} for column {}",
-                            val,
-                            int_max,
+                            "Text '{}' is {} characters, over max_length {} for column {}",
+                            raw,
+                            length,
+                            max_length,
                             name
                         ));
                     }
-                    val
+                    string_table.intern(raw).into()
                 }
-                ColumnSchema::Enum { name, .. } => {
-                    let lookup = enum_lookups[col_idx].as_ref().unwrap();
-                    *lookup.get(raw)
-                        .ok_or_else(|| anyhow::anyhow!(
-                            "Value '{}' not in enum values for column {}",
+                ColumnSchema::List { element, max_length, delimiter, name, .. } => {
+                    let items: Vec<&str> = if raw.is_empty() {
+                        Vec::new()
+                    } else {
+                        raw.split(delimiter.as_str()).map(str::trim).collect()
+                    };
+                    if items.len() > *max_length as usize {
+                        return Err(anyhow::anyhow!(
+                            "List '{}' has {} items, over max_length {} for column {}",
                             raw,
+                            items.len(),
+                            max_length,
                             name
-                        ))?
+                        ));
+                    }
+                    let elements = &mut list_elements[col_idx];
+                    for item in &items {
+                        elements.push(scalar_value(element, item, enum_lookups[col_idx].as_ref())?);
+                    }
+                    items.len() as u128
                 }
+                _ => scalar_value(col, raw, enum_lookups[col_idx].as_ref())?,
             };
             
             col_vals.push(value);
         }
         
+        if col.nullable() {
+            values_by_col.push(presence);
+            bits_by_col.push(1);
+        }
+        if col.kind() == ColumnKind::Text {
+            text_cols.push(values_by_col.len());
+        }
         values_by_col.push(col_vals);
         bits_by_col.push(col.bits());
+        // A list's elements follow its lengths as one stream across all rows.
+        if let ColumnSchema::List { element, .. } = col {
+            values_by_col.push(std::mem::take(&mut list_elements[col_idx]));
+            bits_by_col.push(element.bits());
+        }
     }
     
-    let data_bytes = pack_columnar_bitplanes(&values_by_col, &bits_by_col);
+    // Text index width is only known once every string has been seen.
+    for &i in &text_cols {
+        bits_by_col[i] = string_table.index_bits();
+    }
     
-    let compressed_data = zstd::encode_all(data_bytes.as_slice(), 22)?;
+    let mut flags = 0;
+    let mut data_bytes = Vec::new();
+    if options.embed_schema {
+        flags |= FLAG_EMBEDDED_SCHEMA;
+        data_bytes.extend(schema_to_bytes(schema_to_use));
+    }
+    if schema_to_use.iter().any(|c| c.kind() == ColumnKind::Text) {
+        data_bytes.extend(string_table.to_bytes());
+    }
+    data_bytes.extend(pack_columnar_bitplanes(&values_by_col, &bits_by_col));
     
-    let mut packed = Vec::new();
-    packed.extend_from_slice(b"SCOUTPK5");
-    packed.extend_from_slice(&(num_rows as u32).to_be_bytes());
-    packed.extend_from_slice(&compressed_data);
+    let compressed_data = zstd::encode_all(data_bytes.as_slice(), 22)?;
     
-    Ok(packed)
+    let packed = write_packed(
+        flags,
+        num_rows,
+        schema_fingerprint(schema_to_use)?,
+        builtin_schema_name_for(schema_to_use)?,
+        &compressed_data,
+    )?;
+    Ok(PackResult { packed, warnings })
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::schema::load_schema;
+    use crate::decoder::data_unpacker::decode;
+
+    /// Packs `cells` as the rows of a one-column table whose column is `column`
+    /// (schema JSON without the name).
+    fn pack(column: &str, cells: &[&str]) -> Result<Vec<u8>, anyhow::Error> {
+        let schema = load_schema(Some(format!(r#"[{{ "name": "c", {} }}]"#, column).as_bytes())).unwrap();
+        let rows: Vec<Vec<String>> = cells.iter().map(|cell| vec![cell.to_string()]).collect();
+        encode(&["c".to_string()], &rows, Some(&schema))
+    }
+
+    fn pack_error(column: &str, cells: &[&str]) -> String {
+        pack(column, cells).unwrap_err().to_string()
+    }
+
+    /// Decoded cells of a one-column table packed by `pack`.
+    fn round_trip(column: &str, cells: &[&str]) -> Vec<String> {
+        let schema = load_schema(Some(format!(r#"[{{ "name": "c", {} }}]"#, column).as_bytes())).unwrap();
+        let (_, rows) = decode(&pack(column, cells).unwrap(), Some(&schema)).unwrap();
+        rows.into_iter().map(|mut row| row.remove(0)).collect()
+    }
+
+    #[test]
+    fn bool_cells_must_use_a_known_spelling() {
+        let column = r#""kind": "bool", "true_values": ["scored"], "false_values": ["missed"]"#;
+        assert_eq!(round_trip(column, &["SCORED", "missed", "true"]), ["true", "false", "true"]);
+        let err = pack_error(column, &["scored", "maybe"]);
+        assert_eq!(err, "Invalid boolean value 'maybe' for column c");
+        // The defaults are replaced, not extended.
+        assert!(pack(column, &["yes"]).is_err());
+    }
+
+    #[test]
+    fn text_cells_must_fit_max_length() {
+        let column = r#""kind": "text", "max_length": 5"#;
+        assert_eq!(round_trip(column, &["héllo", "", "héllo", "ok"]), ["héllo", "", "héllo", "ok"]);
+        let err = pack_error(column, &["hello!"]);
+        assert_eq!(err, "Text 'hello!' is 6 characters, over max_length 5 for column c");
+    }
+
+    #[test]
+    fn empty_cells_need_a_nullable_column() {
+        let err = pack_error(r#""kind": "int", "int_max": 9"#, &["3", ""]);
+        assert_eq!(err, "Invalid integer value '' for column c");
+        let err = pack_error(r#""kind": "enum", "values": ["a", "b"]"#, &[" "]);
+        assert_eq!(err, "Value '' not in enum values for column c");
+
+        let nullable = r#""kind": "int", "int_max": 9, "nullable": true"#;
+        assert_eq!(round_trip(nullable, &["3", "", " ", "0"]), ["3", "", "", "0"]);
+    }
+
+    #[test]
+    fn flag_cells_list_known_options_once() {
+        let column = r#""kind": "flags", "options": ["Floor", "Station", "Shelf"]"#;
+        assert_eq!(
+            round_trip(column, &["Shelf|Floor", "", "Station", " | "]),
+            ["Floor|Shelf", "", "Station", ""]
+        );
+        let err = pack_error(column, &["Floor|Roof"]);
+        assert_eq!(err, "Value 'Roof' not in flag options for column c");
+        let err = pack_error(column, &["Floor|Station|Floor"]);
+        assert_eq!(err, "Value 'Floor' is selected twice for column c");
+    }
+
+    #[test]
+    fn zero_bit_cells_must_hold_the_constant() {
+        for (column, constant, other) in [
+            (r#""kind": "constant", "value": 0"#, "0", "1"),
+            (r#""kind": "constant", "value": "n/a""#, "n/a", "N/A"),
+            (r#""kind": "int", "int_min": 7, "int_max": 7"#, "7", "8"),
+            (r#""kind": "enum", "values": ["Only"]"#, "Only", ""),
+        ] {
+            assert_eq!(round_trip(column, &[constant, constant]), [constant, constant]);
+            assert!(pack(column, &[constant, other]).is_err(), "{} accepted {:?}", column, other);
+        }
+    }
+
+    #[test]
+    fn list_cells_are_checked_item_by_item() {
+        let column = r#""kind": "list", "element": { "kind": "int", "int_max": 20 }, "max_length": 3"#;
+        assert_eq!(round_trip(column, &["1|20|3", "", "7"]), ["1|20|3", "", "7"]);
+        let err = pack_error(column, &["1|2|3|4"]);
+        assert_eq!(err, "List '1|2|3|4' has 4 items, over max_length 3 for column c");
+        let err = pack_error(column, &["1|21"]);
+        assert_eq!(err, "Value 21 exceeds int_max 20 for column c");
+        let err = pack_error(column, &["1||3"]);
+        assert_eq!(err, "Invalid integer value '' for column c");
+    }
+}
@@ -1,42 +1,45 @@
 use crate::common::apriltag::generate_april_tags_image;
 use crate::common::color_palette::{calculate_bits_per_pixel, palette_to_bgr, BgrColor};
 use crate::common::constants::DATA_COLOR_SEQUENCE;
-use crate::common::data_regions::get_data_regions;
+use crate::common::data_regions::{get_data_pixel_coords, ImageLayout};
+use crate::common::fec::{add_parity, protected_length, EccLevel};
+use crate::common::header_strip::{header_cells, header_fits, ImageHeader, HEADER_VERSION, MAX_IMAGE_MODULES};
 use crate::encoder::color_encoder::encode_bytes_to_rgb;
+use image::imageops::{resize, FilterType};
 use image::{Rgb, RgbImage};
 
 pub fn calculate_minimum_image_size(
     data_bytes: &[u8],
+    padding: usize,
     tag_data_gap: usize,
     data_padding: usize,
     start_size: usize,
+    ecc_level: EccLevel,
     palette_bgr: &[BgrColor],
 ) -> Result<usize, anyhow::Error> {
+    let bytes_needed = protected_length(data_bytes.len(), ecc_level);
     let bits_per_pixel = calculate_bits_per_pixel(palette_bgr.len());
     let pixels_per_byte = (8.0 / bits_per_pixel as f64).ceil() as usize;
     let num_calibration_colors = palette_bgr.len();
     
     let pixels_needed = if 16 % bits_per_pixel == 0 {
         let pixels_per_2bytes = (16 / bits_per_pixel) as usize;
-        ((data_bytes.len() + 1) / 2) * pixels_per_2bytes + num_calibration_colors
+        bytes_needed.div_ceil(2) * pixels_per_2bytes + num_calibration_colors
     } else {
-        data_bytes.len() * pixels_per_byte + num_calibration_colors
+        bytes_needed * pixels_per_byte + num_calibration_colors
     };
     
     let mut image_size = start_size;
-    while image_size <= 1000 {
-        let data_regions = get_data_regions(image_size, image_size, tag_data_gap, data_padding);
-        let mut pixel_coords: Vec<(usize, usize)> = Vec::new();
-        
-        for region in &data_regions {
-            for row in region.row_start..region.row_end {
-                for col in region.col_start..region.col_end {
-                    pixel_coords.push((row, col));
-                }
-            }
-        }
+    while image_size <= MAX_IMAGE_MODULES {
+        let layout = ImageLayout {
+            image_width: image_size,
+            image_height: image_size,
+            padding,
+            tag_data_gap,
+            data_padding,
+        };
         
-        if pixel_coords.len() >= pixels_needed {
+        if header_fits(&layout) && get_data_pixel_coords(&layout).len() >= pixels_needed {
             return Ok(image_size);
         }
         
@@ -44,50 +47,63 @@ pub fn calculate_minimum_image_size(
     }
     
     Err(anyhow::anyhow!(
-        "Cannot find suitable image size for {} bytes of data",
-        data_bytes.len()
+        "Cannot find suitable image size for {} bytes of data (the header strip records at most {} modules per side)",
+        data_bytes.len(),
+        MAX_IMAGE_MODULES
     ))
 }
 
+/// Renders the tags, header strip and data (with `ecc_level` parity appended),
+/// with every module drawn as a `module_size` x `module_size` block of pixels.
 pub fn create_encoded_image(
-    data_bytes: &[u8],
-    image_width: usize,
-    image_height: usize,
-    padding: usize,
-    tag_data_gap: usize,
-    data_padding: usize,
+    packed_bytes: &[u8],
+    layout: &ImageLayout,
+    module_size: usize,
+    ecc_level: EccLevel,
     palette_bgr: Option<&[BgrColor]>,
 ) -> Result<RgbImage, anyhow::Error> {
     let palette_bgr = match palette_bgr {
         Some(p) => p,
         None => {
-            let default_palette_rgb: Vec<_> = DATA_COLOR_SEQUENCE.iter().copied().collect();
+            let default_palette_rgb: Vec<_> = DATA_COLOR_SEQUENCE.to_vec();
             &palette_to_bgr(&default_palette_rgb)
         }
     };
     
-    let mut image = generate_april_tags_image(image_width, image_height, padding)?;
-    
-    let data_regions = get_data_regions(image_width, image_height, tag_data_gap, data_padding);
+    if !header_fits(layout) {
+        return Err(anyhow::anyhow!(
+            "Image width {} leaves no room for the header strip between the top tags",
+            layout.image_width
+        ));
+    }
     
-    let encoded_colors = encode_bytes_to_rgb(data_bytes, palette_bgr);
+    let mut image = generate_april_tags_image(layout.image_width, layout.image_height, layout.padding)?;
     
-    let mut pixel_coords: Vec<(usize, usize)> = Vec::new();
-    for region in &data_regions {
-        for row in region.row_start..region.row_end {
-            for col in region.col_start..region.col_end {
-                pixel_coords.push((row, col));
-            }
-        }
+    let header = ImageHeader {
+        version: HEADER_VERSION,
+        bits_per_pixel: calculate_bits_per_pixel(palette_bgr.len()),
+        module_size,
+        layout: *layout,
+        ecc_level,
+        payload_length: packed_bytes.len(),
+    };
+    for (bit, (row, col)) in header.to_bits()?.into_iter().zip(header_cells(layout.padding)) {
+        let value = if bit { 0 } else { 255 };
+        image.put_pixel(col as u32, row as u32, Rgb([value, value, value]));
     }
     
+    let data_bytes = add_parity(packed_bytes, ecc_level);
+    let encoded_colors = encode_bytes_to_rgb(&data_bytes, palette_bgr);
+    
+    let pixel_coords = get_data_pixel_coords(layout);
+    
     let bytes_needed = data_bytes.len();
     let bits_per_pixel = calculate_bits_per_pixel(palette_bgr.len());
     let pixels_per_byte = (8.0 / bits_per_pixel as f64).ceil() as usize;
     
     let pixels_needed = if 16 % bits_per_pixel == 0 {
         let pixels_per_2bytes = (16 / bits_per_pixel) as usize;
-        ((bytes_needed + 1) / 2) * pixels_per_2bytes
+        bytes_needed.div_ceil(2) * pixels_per_2bytes
     } else {
         bytes_needed * pixels_per_byte
     };
@@ -114,7 +130,7 @@ pub fn create_encoded_image(
     }
     
     // Place calibration pixels at the very end of data regions
-    let calibration_colors: Vec<BgrColor> = palette_bgr.iter().copied().collect();
+    let calibration_colors: Vec<BgrColor> = palette_bgr.to_vec();
     
     for (i, &calibration_color) in calibration_colors.iter().enumerate() {
         let coord_index = pixels_available - calibration_pixels_needed + i;
@@ -122,6 +138,15 @@ pub fn create_encoded_image(
         image.put_pixel(col as u32, row as u32, Rgb([calibration_color.2, calibration_color.1, calibration_color.0]));
     }
     
+    if module_size > 1 {
+        image = resize(
+            &image,
+            (layout.image_width * module_size) as u32,
+            (layout.image_height * module_size) as u32,
+            FilterType::Nearest,
+        );
+    }
+    
     Ok(image)
 }
 
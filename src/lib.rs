@@ -1,4 +1,4 @@
 pub mod api;
 pub mod common;
+pub mod decoder;
 pub mod encoder;
-
@@ -7,20 +7,66 @@ pub fn encode_csv_to_image(
     csv: &[u8],
     schema: Option<Vec<u8>>,
     palette: Option<Vec<u8>>,
+    ecc: Option<String>,
+    embed_schema: Option<bool>,
 ) -> Result<Vec<u8>, JsValue> {
-    // Load schema separately so we can surface resolution warnings to the browser console.
-    let (_, warnings) =
-        scouting_data_compression::common::schema::load_schema_with_warnings(schema.as_deref())
+    let mut options = scouting_data_compression::api::EncodeOptions::default();
+    if let Some(ecc) = ecc {
+        options.ecc_level = ecc
+            .parse::<scouting_data_compression::common::fec::EccLevel>()
             .map_err(|e| JsValue::from_str(&e.to_string()))?;
-    for w in &warnings {
-        web_sys::console::warn_1(&JsValue::from_str(&format!("[schema] {}", w)));
     }
+    options.embed_schema = embed_schema.unwrap_or(false);
 
-    let result = scouting_data_compression::api::encode_csv_to_image(
+    let result = scouting_data_compression::api::encode_csv_to_image_with_options(
         csv,
         schema.as_deref(),
         palette.as_deref(),
+        &options,
     )
     .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    // Schema and rule warnings go to the browser console.
+    for w in &result.warnings {
+        web_sys::console::warn_1(&JsValue::from_str(&format!("[warning] {}", w)));
+    }
     Ok(result.image_bytes)
 }
+
+#[wasm_bindgen]
+pub fn decode_image_to_csv(
+    png: &[u8],
+    schema: Option<Vec<u8>>,
+    palette: Option<Vec<u8>>,
+) -> Result<Vec<u8>, JsValue> {
+    let result = scouting_data_compression::api::decode_image_to_csv(
+        png,
+        schema.as_deref(),
+        palette.as_deref(),
+    )
+    .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    Ok(result.csv_bytes)
+}
+
+#[wasm_bindgen]
+pub fn decode_photo_to_csv(
+    photo: &[u8],
+    schema: Option<Vec<u8>>,
+    palette: Option<Vec<u8>>,
+) -> Result<Vec<u8>, JsValue> {
+    let result = scouting_data_compression::api::decode_photo_to_csv(
+        photo,
+        schema.as_deref(),
+        palette.as_deref(),
+    )
+    .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    Ok(result.csv_bytes)
+}
+
+/// Names accepted in place of schema JSON, e.g. `frc-2025-reefscape`.
+#[wasm_bindgen]
+pub fn builtin_schema_names() -> Vec<String> {
+    scouting_data_compression::common::schema_registry::builtin_schema_names()
+        .into_iter()
+        .map(String::from)
+        .collect()
+}